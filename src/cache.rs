@@ -0,0 +1,343 @@
+//! On-disk cache of forge lookups, so generating the notes for the same range again is cheap
+
+use anyhow::Result;
+use async_trait::async_trait;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::forge::{BulkLookup, ChangeRequest, Forge, RemoteRepo, Revalidated};
+
+/// Directory below the user's cache directory
+pub const CACHE_DIR_NAME: &str = "git-releasenotes";
+
+/// Cached change request of a commit
+#[derive(Serialize, Deserialize)]
+struct CommitEntry {
+    number: u64,
+}
+
+/// Cached change request details, with the ETag to check whether they are still current
+#[derive(Serialize, Deserialize)]
+struct ChangeRequestEntry {
+    etag: Option<String>,
+    change_request: Option<ChangeRequest>,
+}
+
+/// `$XDG_CACHE_HOME/git-releasenotes`, or `~/.cache/git-releasenotes`
+pub fn default_cache_dir() -> Option<PathBuf> {
+    cache_dir_from(
+        std::env::var_os("XDG_CACHE_HOME").map(PathBuf::from),
+        std::env::var_os("HOME").map(PathBuf::from),
+    )
+}
+
+fn cache_dir_from(xdg_cache_home: Option<PathBuf>, home: Option<PathBuf>) -> Option<PathBuf> {
+    // Relative paths are invalid in XDG variables and are ignored
+    xdg_cache_home
+        .filter(|dir| dir.is_absolute())
+        .or_else(|| home.map(|home| home.join(".cache")))
+        .map(|dir| dir.join(CACHE_DIR_NAME))
+}
+
+/// A forge whose lookups are cached on disk, per repository
+///
+/// The change request of a commit is cached once found; commits without one are looked up
+/// again next time, as they may have been pushed or merged since. Change request details are
+/// revalidated with their ETag, which is much cheaper than fetching them again.
+pub struct Cached {
+    forge: Box<dyn Forge>,
+    dir: PathBuf,
+    /// Ignore cached entries, but store what is looked up
+    refresh: bool,
+}
+
+/// Keeps temporary file names apart when the same entry is written concurrently
+static TEMP_FILE_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+impl Cached {
+    /// Cache the lookups of `forge` for `remote` below `root`
+    pub fn new(forge: Box<dyn Forge>, root: &Path, remote: &RemoteRepo, refresh: bool) -> Self {
+        // Only plain path components, so an odd remote URL can't point outside the cache
+        let mut dir = root.join(&remote.host);
+        for component in Path::new(&remote.path).components() {
+            if let Component::Normal(name) = component {
+                dir.push(name);
+            }
+        }
+        Cached {
+            forge,
+            dir,
+            refresh,
+        }
+    }
+
+    fn commit_path(&self, sha: &str) -> Option<PathBuf> {
+        // The SHA becomes a file name, so anything but an object ID isn't cached
+        (!sha.is_empty() && sha.chars().all(|c| c.is_ascii_hexdigit()))
+            .then(|| self.dir.join("commits").join(format!("{}.json", sha)))
+    }
+
+    fn change_request_path(&self, number: u64) -> PathBuf {
+        self.dir
+            .join("change-requests")
+            .join(format!("{}.json", number))
+    }
+
+    /// An entry, `None` when missing, unreadable or refreshing
+    fn read<T: DeserializeOwned>(&self, path: &Path) -> Option<T> {
+        if self.refresh {
+            return None;
+        }
+        let content = std::fs::read_to_string(path).ok()?;
+        serde_json::from_str(&content).ok()
+    }
+
+    /// Store an entry; the cache is best effort, so failing to write it is no error
+    fn write<T: Serialize>(&self, path: &Path, value: &T) {
+        let _ = Self::try_write(path, value);
+    }
+
+    fn try_write<T: Serialize>(path: &Path, value: &T) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        // Write to a temporary file first, so readers never see half an entry
+        let temp = path.with_extension(format!(
+            "{}-{}.tmp",
+            std::process::id(),
+            TEMP_FILE_COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        std::fs::write(&temp, serde_json::to_vec(value)?)?;
+        std::fs::rename(&temp, path).inspect_err(|_| {
+            let _ = std::fs::remove_file(&temp);
+        })?;
+        Ok(())
+    }
+
+    fn store_commit(&self, sha: &str, number: Option<u64>) {
+        if let (Some(path), Some(number)) = (self.commit_path(sha), number) {
+            self.write(&path, &CommitEntry { number });
+        }
+    }
+}
+
+#[async_trait]
+impl Forge for Cached {
+    async fn find_change_request(&self, sha: &str) -> Result<Option<u64>> {
+        let path = self.commit_path(sha);
+        if let Some(entry) = path.as_deref().and_then(|p| self.read::<CommitEntry>(p)) {
+            return Ok(Some(entry.number));
+        }
+        let number = self.forge.find_change_request(sha).await?;
+        self.store_commit(sha, number);
+        Ok(number)
+    }
+
+    async fn find_change_requests(&self, shas: &[String]) -> BulkLookup {
+        let mut found = HashMap::new();
+        let mut missing = Vec::new();
+        for sha in shas {
+            let path = self.commit_path(sha);
+            match path.as_deref().and_then(|p| self.read::<CommitEntry>(p)) {
+                Some(entry) => {
+                    found.insert(sha.clone(), Some(entry.number));
+                }
+                None => missing.push(sha.clone()),
+            }
+        }
+        if missing.is_empty() {
+            return BulkLookup { found, error: None };
+        }
+        // What was found before a failure is kept, and cached
+        let looked_up = self.forge.find_change_requests(&missing).await;
+        for (sha, number) in &looked_up.found {
+            self.store_commit(sha, *number);
+        }
+        found.extend(looked_up.found);
+        BulkLookup {
+            found,
+            error: looked_up.error,
+        }
+    }
+
+    async fn change_request(&self, number: u64) -> Result<Option<ChangeRequest>> {
+        let path = self.change_request_path(number);
+        let cached: Option<ChangeRequestEntry> = self.read(&path);
+        let etag = cached.as_ref().and_then(|entry| entry.etag.as_deref());
+        match self.forge.change_request_if_modified(number, etag).await? {
+            Revalidated::NotModified if cached.is_some() => {
+                Ok(cached.and_then(|entry| entry.change_request))
+            }
+            // Only possible with a forge answering requests it didn't get an ETag for
+            Revalidated::NotModified => self.forge.change_request(number).await,
+            Revalidated::Modified {
+                change_request,
+                etag,
+            } => {
+                // Without an ETag the entry could never be used
+                if etag.is_some() {
+                    let entry = ChangeRequestEntry {
+                        etag,
+                        change_request,
+                    };
+                    self.write(&path, &entry);
+                    return Ok(entry.change_request);
+                }
+                Ok(change_request)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    /// Forge that records its lookups; commits starting with "a" have PR 1, ETags are "v1"
+    #[derive(Default)]
+    struct RecordingForge {
+        lookups: Mutex<Vec<String>>,
+    }
+
+    #[async_trait]
+    impl Forge for Arc<RecordingForge> {
+        async fn find_change_request(&self, sha: &str) -> Result<Option<u64>> {
+            self.lookups.lock().unwrap().push(format!("sha {}", sha));
+            Ok(sha.starts_with('a').then_some(1))
+        }
+
+        async fn find_change_requests(&self, shas: &[String]) -> BulkLookup {
+            self.lookups
+                .lock()
+                .unwrap()
+                .push(format!("bulk {}", shas.join(",")));
+            BulkLookup {
+                found: shas
+                    .iter()
+                    .map(|sha| (sha.clone(), sha.starts_with('a').then_some(1)))
+                    .collect(),
+                error: None,
+            }
+        }
+
+        async fn change_request(&self, number: u64) -> Result<Option<ChangeRequest>> {
+            self.lookups.lock().unwrap().push(format!("pr {}", number));
+            Ok(None)
+        }
+
+        async fn change_request_if_modified(
+            &self,
+            number: u64,
+            etag: Option<&str>,
+        ) -> Result<Revalidated> {
+            self.lookups
+                .lock()
+                .unwrap()
+                .push(format!("pr {} {:?}", number, etag));
+            if etag == Some("v1") {
+                return Ok(Revalidated::NotModified);
+            }
+            Ok(Revalidated::Modified {
+                change_request: Some(ChangeRequest {
+                    number,
+                    title: format!("PR {}", number),
+                    ..Default::default()
+                }),
+                etag: Some("v1".to_string()),
+            })
+        }
+    }
+
+    fn remote() -> RemoteRepo {
+        RemoteRepo::parse("git@github.com:owner/repo.git").unwrap()
+    }
+
+    fn cached(root: &Path, forge: &Arc<RecordingForge>, refresh: bool) -> Cached {
+        Cached::new(Box::new(forge.clone()), root, &remote(), refresh)
+    }
+
+    fn take_lookups(forge: &RecordingForge) -> Vec<String> {
+        std::mem::take(&mut *forge.lookups.lock().unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_commit_lookups_are_cached() {
+        let root = tempfile::tempdir().unwrap();
+        let forge = Arc::new(RecordingForge::default());
+        let shas = vec!["a1".to_string(), "b2".to_string()];
+
+        let cache = cached(root.path(), &forge, false);
+        let found = cache.find_change_requests(&shas).await.found;
+        assert_eq!(found["a1"], Some(1));
+        assert_eq!(found["b2"], None);
+        assert!(root
+            .path()
+            .join("github.com/owner/repo/commits/a1.json")
+            .is_file());
+
+        // Commits without a PR are looked up again, in case they got one since
+        let cache = cached(root.path(), &forge, false);
+        let found = cache.find_change_requests(&shas).await.found;
+        assert_eq!(found["a1"], Some(1));
+        assert_eq!(cache.find_change_request("a1").await.unwrap(), Some(1));
+        assert_eq!(cache.find_change_request("b2").await.unwrap(), None);
+        assert_eq!(
+            take_lookups(&forge),
+            vec!["bulk a1,b2", "bulk b2", "sha b2"]
+        );
+
+        let cache = cached(root.path(), &forge, true);
+        assert_eq!(cache.find_change_request("a1").await.unwrap(), Some(1));
+        assert_eq!(take_lookups(&forge), vec!["sha a1"]);
+    }
+
+    #[tokio::test]
+    async fn test_change_requests_are_revalidated() {
+        let root = tempfile::tempdir().unwrap();
+        let forge = Arc::new(RecordingForge::default());
+
+        let cache = cached(root.path(), &forge, false);
+        let first = cache.change_request(5).await.unwrap();
+        assert_eq!(first.as_ref().unwrap().title, "PR 5");
+
+        let cache = cached(root.path(), &forge, false);
+        assert_eq!(cache.change_request(5).await.unwrap(), first);
+
+        // Refreshing fetches the details again instead of revalidating them
+        let cache = cached(root.path(), &forge, true);
+        assert_eq!(cache.change_request(5).await.unwrap(), first);
+        assert_eq!(
+            take_lookups(&forge),
+            vec!["pr 5 None", "pr 5 Some(\"v1\")", "pr 5 None"]
+        );
+    }
+
+    #[test]
+    fn test_cache_location() {
+        let remote = RemoteRepo {
+            host: "gitlab.example.com".to_string(),
+            path: "group/../../project".to_string(),
+        };
+        let forge = Arc::new(RecordingForge::default());
+        let cache = Cached::new(Box::new(forge), Path::new("/cache"), &remote, false);
+        assert_eq!(
+            cache.dir,
+            Path::new("/cache/gitlab.example.com/group/project")
+        );
+        assert_eq!(cache.commit_path("../x"), None);
+
+        assert_eq!(
+            cache_dir_from(Some("/xdg".into()), Some("/home/dev".into())),
+            Some(PathBuf::from("/xdg/git-releasenotes"))
+        );
+        assert_eq!(
+            cache_dir_from(Some("relative".into()), Some("/home/dev".into())),
+            Some(PathBuf::from("/home/dev/.cache/git-releasenotes"))
+        );
+        assert_eq!(cache_dir_from(None, None), None);
+    }
+}
@@ -0,0 +1,398 @@
+use anyhow::{anyhow, Result};
+use chrono::NaiveDate;
+use regex::Regex;
+
+use crate::conventional::{CATEGORY_FEATURES, CATEGORY_FIXES};
+use crate::{dependency_lines, Change, ReleaseNotes, CATEGORY_DEPENDENCIES};
+
+/// File name used when `--file` isn't given, at the root of the repository
+pub const CHANGELOG_FILE_NAME: &str = "CHANGELOG.md";
+
+/// Category key that is listed under "Security", for user-defined categories
+pub const CATEGORY_SECURITY: &str = "security";
+
+/// Start of a new changelog, used when the file doesn't exist yet
+const NEW_CHANGELOG: &str = "# Changelog
+
+All notable changes to this project will be documented in this file.
+
+The format is based on [Keep a Changelog](https://keepachangelog.com/en/1.1.0/),
+and this project adheres to [Semantic Versioning](https://semver.org/spec/v2.0.0.html).
+
+## [Unreleased]
+";
+
+/// Keep a Changelog sub-sections, in the order they are rendered
+const KINDS: [&str; 5] = ["Added", "Changed", "Fixed", "Security", "Dependencies"];
+
+/// Where the compare links at the bottom of the changelog point
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompareLinks {
+    /// Repository web URL, e.g. `https://github.com/owner/repo`
+    pub base_url: String,
+    pub previous_tag: String,
+    pub tag: String,
+}
+
+/// Keep a Changelog sub-section a change is listed under
+fn kind(change: &Change) -> &'static str {
+    match change.category.as_str() {
+        CATEGORY_FEATURES => "Added",
+        CATEGORY_FIXES => "Fixed",
+        CATEGORY_SECURITY => "Security",
+        CATEGORY_DEPENDENCIES => "Dependencies",
+        _ => "Changed",
+    }
+}
+
+/// Version a tag like `v1.3.0` or `1.3.0` stands for, `None` if it doesn't look like one
+pub fn version_from_tag(tag: &str) -> Option<String> {
+    let re = Regex::new(r"^v?(\d+(?:\.\d+)*(?:[-+][0-9A-Za-z.-]+)?)$").unwrap();
+    re.captures(tag).map(|caps| caps[1].to_string())
+}
+
+/// Check a release date given as `YYYY-MM-DD`, as Keep a Changelog writes it
+pub fn parse_release_date(date: &str) -> Result<String> {
+    let parsed = NaiveDate::parse_from_str(date.trim(), "%Y-%m-%d")
+        .map_err(|e| anyhow!("Invalid date '{}', expected YYYY-MM-DD: {}", date, e))?;
+    Ok(parsed.format("%Y-%m-%d").to_string())
+}
+
+/// Render the notes as a `## [version] - date` section, see [`crate::generate_release_notes`]
+pub fn render_section(
+    notes: &ReleaseNotes,
+    version: &str,
+    date: &str,
+    pr_sigil: Option<char>,
+) -> String {
+    let mut lines = vec![format!("## [{}] - {}", version, date)];
+
+    for kind_name in KINDS {
+        let mut entries: Vec<String> = if kind_name == "Dependencies" {
+            dependency_lines(notes, pr_sigil)
+        } else {
+            notes
+                .changes
+                .iter()
+                .filter(|c| c.category != CATEGORY_DEPENDENCIES && kind(c) == kind_name)
+                .map(|c| {
+                    let marker = if c.breaking { "**Breaking:** " } else { "" };
+                    format!("- {}{} ({})", marker, c.title(pr_sigil), c.author)
+                })
+                .collect()
+        };
+        if entries.is_empty() {
+            continue;
+        }
+        entries.sort();
+        entries.dedup();
+        lines.push(String::new());
+        lines.push(format!("### {}", kind_name));
+        lines.push(String::new());
+        lines.extend(entries);
+    }
+
+    lines.join("\n")
+}
+
+fn is_release_heading(line: &str) -> bool {
+    line.starts_with("## ")
+}
+
+fn is_unreleased_heading(line: &str) -> bool {
+    is_release_heading(line)
+        && line[3..]
+            .trim_start_matches('[')
+            .to_lowercase()
+            .starts_with("unreleased")
+}
+
+fn is_version_heading(line: &str, version: &str) -> bool {
+    is_release_heading(line)
+        && (line[3..].starts_with(&format!("[{}]", version))
+            || line[3..].split_whitespace().next() == Some(version))
+}
+
+/// Label of a link reference definition like `[1.2.0]: https://...`
+fn link_label(line: &str) -> Option<&str> {
+    let rest = line.strip_prefix('[')?;
+    let (label, _) = rest.split_once("]:")?;
+    Some(label)
+}
+
+/// Insert `section` into an existing changelog, or a new one when `existing` is `None`
+///
+/// The section goes right after the `Unreleased` section; if the version is already there its
+/// section is replaced instead, so running this twice for the same release changes nothing.
+/// With `links`, the `[unreleased]` and `[version]` compare links at the bottom are updated.
+pub fn insert_release(
+    existing: Option<&str>,
+    version: &str,
+    section: &str,
+    links: Option<&CompareLinks>,
+) -> String {
+    let existing = existing
+        .filter(|content| !content.trim().is_empty())
+        .unwrap_or(NEW_CHANGELOG);
+    let lines: Vec<&str> = existing.lines().collect();
+
+    // Link reference definitions (and blank lines between them) at the end of the file
+    let mut link_start = lines.len();
+    while link_start > 0 {
+        let line = lines[link_start - 1];
+        if line.trim().is_empty() || link_label(line).is_some() {
+            link_start -= 1;
+        } else {
+            break;
+        }
+    }
+    let body = &lines[..link_start];
+    let mut link_lines: Vec<String> = lines[link_start..]
+        .iter()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.to_string())
+        .collect();
+
+    let next_heading = |from: usize| {
+        body[from..]
+            .iter()
+            .position(|line| is_release_heading(line))
+            .map_or(body.len(), |i| from + i)
+    };
+    let (start, end) = if let Some(i) = body.iter().position(|l| is_version_heading(l, version)) {
+        (i, next_heading(i + 1))
+    } else if let Some(i) = body.iter().position(|l| is_unreleased_heading(l)) {
+        let pos = next_heading(i + 1);
+        (pos, pos)
+    } else {
+        let pos = next_heading(0);
+        (pos, pos)
+    };
+
+    let mut before: Vec<&str> = body[..start].to_vec();
+    while before.last().is_some_and(|line| line.trim().is_empty()) {
+        before.pop();
+    }
+    let after: Vec<&str> = body[end..]
+        .iter()
+        .copied()
+        .skip_while(|line| line.trim().is_empty())
+        .collect();
+
+    let mut out: Vec<String> = before.iter().map(|line| line.to_string()).collect();
+    if !out.is_empty() {
+        out.push(String::new());
+    }
+    out.extend(section.lines().map(|line| line.to_string()));
+    if !after.is_empty() {
+        out.push(String::new());
+        out.extend(after.iter().map(|line| line.to_string()));
+    }
+    while out.last().is_some_and(|line| line.trim().is_empty()) {
+        out.pop();
+    }
+
+    if let Some(links) = links {
+        let unreleased = format!(
+            "[unreleased]: {}/compare/{}...HEAD",
+            links.base_url, links.tag
+        );
+        let release = format!(
+            "[{}]: {}/compare/{}...{}",
+            version, links.base_url, links.previous_tag, links.tag
+        );
+        link_lines.retain(|line| {
+            link_label(line)
+                .is_some_and(|label| !label.eq_ignore_ascii_case("unreleased") && label != version)
+        });
+        link_lines.insert(0, release);
+        link_lines.insert(0, unreleased);
+    }
+    if !link_lines.is_empty() {
+        out.push(String::new());
+        out.extend(link_lines);
+    }
+
+    out.join("\n") + "\n"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DependencyUpdate, RangeInfo, CATEGORY_OTHER};
+
+    fn change(subject: &str, category: &str, breaking: bool) -> Change {
+        Change {
+            subject: subject.to_string(),
+            author: "User".to_string(),
+            hash: "sha".to_string(),
+            pr_number: None,
+            category: category.to_string(),
+            scope: None,
+            breaking,
+            labels: Vec::new(),
+            login: None,
+            updates: Vec::new(),
+        }
+    }
+
+    fn notes() -> ReleaseNotes {
+        ReleaseNotes {
+            range: RangeInfo::default(),
+            changes: vec![
+                change("Add export", CATEGORY_FEATURES, false),
+                change("Drop old API", CATEGORY_OTHER, true),
+                change("Fix crash", CATEGORY_FIXES, false),
+                change("Escape input", CATEGORY_SECURITY, false),
+            ],
+            dependency_updates: vec![DependencyUpdate::new("serde", "1.0.1", "1.0.2", Some(7))],
+            contributors: Vec::new(),
+        }
+    }
+
+    const EXPECTED_SECTION: &str = "## [1.3.0] - 2024-05-01
+
+### Added
+
+- Add export (User)
+
+### Changed
+
+- **Breaking:** Drop old API (User)
+
+### Fixed
+
+- Fix crash (User)
+
+### Security
+
+- Escape input (User)
+
+### Dependencies
+
+- Updates `serde` from 1.0.1 to 1.0.2";
+
+    fn links() -> CompareLinks {
+        CompareLinks {
+            base_url: "https://github.com/owner/repo".to_string(),
+            previous_tag: "v1.2.0".to_string(),
+            tag: "v1.3.0".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_version_from_tag() {
+        assert_eq!(version_from_tag("v1.3.0").as_deref(), Some("1.3.0"));
+        assert_eq!(version_from_tag("2.0").as_deref(), Some("2.0"));
+        assert_eq!(
+            version_from_tag("v1.0.0-rc.1").as_deref(),
+            Some("1.0.0-rc.1")
+        );
+        assert_eq!(version_from_tag("HEAD"), None);
+        assert_eq!(version_from_tag("release/1.3"), None);
+    }
+
+    #[test]
+    fn test_parse_release_date() {
+        assert_eq!(parse_release_date("2024-03-01").unwrap(), "2024-03-01");
+        assert!(parse_release_date("2024-02-30").is_err());
+        assert!(parse_release_date("01.03.2024").is_err());
+        assert!(parse_release_date("next friday").is_err());
+        assert!(parse_release_date("2024-03-01T10:00").is_err());
+    }
+
+    #[test]
+    fn test_render_section() {
+        assert_eq!(
+            render_section(&notes(), "1.3.0", "2024-05-01", None),
+            EXPECTED_SECTION
+        );
+    }
+
+    #[test]
+    fn test_insert_into_new_changelog() {
+        let section = render_section(&notes(), "1.3.0", "2024-05-01", None);
+        let content = insert_release(None, "1.3.0", &section, Some(&links()));
+        assert!(content.starts_with(NEW_CHANGELOG));
+        assert!(content.contains(&format!("## [Unreleased]\n\n{}\n", EXPECTED_SECTION)));
+        assert!(content.ends_with(
+            "\n\n[unreleased]: https://github.com/owner/repo/compare/v1.3.0...HEAD\n\
+             [1.3.0]: https://github.com/owner/repo/compare/v1.2.0...v1.3.0\n"
+        ));
+    }
+
+    #[test]
+    fn test_insert_below_unreleased_and_update_links() {
+        let existing = "# Changelog
+
+## [Unreleased]
+
+### Added
+
+- Something not released yet
+
+## [1.2.0] - 2024-01-01
+
+### Fixed
+
+- Old fix
+
+[unreleased]: https://github.com/owner/repo/compare/v1.2.0...HEAD
+[1.2.0]: https://github.com/owner/repo/compare/v1.1.0...v1.2.0
+";
+        let section = "## [1.3.0] - 2024-05-01\n\n### Fixed\n\n- New fix (User)";
+        let expected = "# Changelog
+
+## [Unreleased]
+
+### Added
+
+- Something not released yet
+
+## [1.3.0] - 2024-05-01
+
+### Fixed
+
+- New fix (User)
+
+## [1.2.0] - 2024-01-01
+
+### Fixed
+
+- Old fix
+
+[unreleased]: https://github.com/owner/repo/compare/v1.3.0...HEAD
+[1.3.0]: https://github.com/owner/repo/compare/v1.2.0...v1.3.0
+[1.2.0]: https://github.com/owner/repo/compare/v1.1.0...v1.2.0
+";
+        let content = insert_release(Some(existing), "1.3.0", section, Some(&links()));
+        assert_eq!(content, expected);
+
+        // Running again for the same release leaves the file as it is
+        assert_eq!(
+            insert_release(Some(&content), "1.3.0", section, Some(&links())),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_insert_replaces_existing_release() {
+        let existing =
+            "# Changelog\n\n## [1.3.0] - 2024-04-30\n\n- stale\n\n## [1.2.0] - 2024-01-01\n";
+        let section = "## [1.3.0] - 2024-05-01\n\n- fresh";
+        assert_eq!(
+            insert_release(Some(existing), "1.3.0", section, None),
+            "# Changelog\n\n## [1.3.0] - 2024-05-01\n\n- fresh\n\n## [1.2.0] - 2024-01-01\n"
+        );
+    }
+
+    #[test]
+    fn test_insert_without_unreleased_heading() {
+        let existing = "# Changelog\n\nIntro\n\n## 1.2.0\n\n- old\n";
+        let section = "## [1.3.0] - 2024-05-01\n\n- new";
+        assert_eq!(
+            insert_release(Some(existing), "1.3.0", section, None),
+            "# Changelog\n\nIntro\n\n## [1.3.0] - 2024-05-01\n\n- new\n\n## 1.2.0\n\n- old\n"
+        );
+    }
+}
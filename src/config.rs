@@ -0,0 +1,606 @@
+use anyhow::{anyhow, Context, Result};
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Deserializer};
+use std::path::Path;
+
+use crate::conventional::{
+    CATEGORY_FEATURES, CATEGORY_FIXES, CATEGORY_PERFORMANCE, CATEGORY_REFACTORING,
+};
+use crate::forge::ForgeKind;
+use crate::version::UpdateKind;
+
+/// File name looked up at the root of the repository
+pub const CONFIG_FILE_NAME: &str = ".releasenotes.toml";
+
+/// Per-repository settings read from `.releasenotes.toml`
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Branch that releases are cut from, detected from the remote's HEAD when not set
+    pub branch: Option<String>,
+    /// Remote to fetch from and pull the branch from, `origin` when not set
+    pub remote: Option<String>,
+    /// Where pull or merge requests are looked up, detected from the remote URL when not set
+    pub forge: Option<ForgeKind>,
+    /// API root of a GitHub Enterprise Server, e.g. `https://github.example.com/api/v3`
+    pub github_api_url: Option<String>,
+    /// List a PR by its title and author instead of its commits' subjects, once per PR
+    pub pr_titles: bool,
+    /// Commits whose subject matches any of these patterns (case-insensitive) are left out
+    #[serde(deserialize_with = "case_insensitive_patterns")]
+    pub skip: Vec<Pattern>,
+    /// Authors containing any of these strings (case-insensitive) are treated as dependency bots
+    pub bot_authors: Vec<String>,
+    /// Commits whose PR has any of these labels (case-insensitive) are left out
+    pub skip_labels: Vec<String>,
+    /// Commits by these authors, by commit author name or PR author login, are left out
+    pub skip_authors: Vec<String>,
+    /// Commits whose PR has any of these labels are listed as breaking changes
+    pub breaking_labels: Vec<String>,
+    /// Category sections, in the order they are rendered
+    pub categories: Vec<CategoryRule>,
+    /// Apply `.github/release.yml` from the end of the range, see [`crate::release_yml`]
+    pub use_release_yml: bool,
+    /// End the notes with a section listing the authors of the range
+    pub contributors: bool,
+    /// List dependency changes in lockfiles too, not only those made by bots, transitive ones included
+    pub lockfiles: bool,
+    /// Smallest dependency update that is warned about above the dependencies
+    pub version_warning: VersionWarning,
+    pub headings: Headings,
+    /// Defaults for command line flags
+    pub defaults: Defaults,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            branch: None,
+            remote: None,
+            forge: None,
+            github_api_url: None,
+            pr_titles: false,
+            skip: vec![Pattern::case_insensitive("setting new snapshot version")],
+            bot_authors: vec!["dependabot".to_string(), "renovate".to_string()],
+            skip_labels: vec!["skip-changelog".to_string()],
+            skip_authors: Vec::new(),
+            breaking_labels: vec!["breaking".to_string(), "breaking-change".to_string()],
+            categories: vec![
+                CategoryRule::for_types(CATEGORY_FEATURES, "Features", &["feat", "feature"]),
+                CategoryRule::for_types(CATEGORY_FIXES, "Bug fixes", &["fix", "bugfix"]),
+                CategoryRule::for_types(CATEGORY_PERFORMANCE, "Performance", &["perf"]),
+                CategoryRule::for_types(CATEGORY_REFACTORING, "Refactoring", &["refactor"]),
+            ],
+            use_release_yml: true,
+            contributors: true,
+            lockfiles: false,
+            version_warning: VersionWarning::Major,
+            headings: Headings::default(),
+            defaults: Defaults::default(),
+        }
+    }
+}
+
+/// What categories and exclusions are decided on for a commit
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CommitFacts<'a> {
+    /// Conventional Commits type
+    pub kind: Option<&'a str>,
+    pub subject: &'a str,
+    pub body: &'a str,
+    /// Author name from the commit
+    pub author: &'a str,
+    /// Login of whoever opened the commit's PR
+    pub login: Option<&'a str>,
+    pub paths: &'a [String],
+    /// Labels of the commit's PR
+    pub labels: &'a [String],
+}
+
+/// A section of the notes and the rule deciding which commits belong in it
+///
+/// All criteria that are set must match; a rule without any criteria never matches.
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct CategoryRule {
+    pub key: String,
+    pub heading: String,
+    /// Conventional Commits types, e.g. `["feat"]`
+    #[serde(default)]
+    pub types: Vec<String>,
+    /// Regex matched against the commit subject
+    pub subject: Option<Pattern>,
+    /// Regex matched against the commit body
+    pub body: Option<Pattern>,
+    /// Regex matched against the author name
+    pub author: Option<Pattern>,
+    /// Regex matched against the paths changed by the commit
+    pub path: Option<Pattern>,
+    /// Labels of the commit's PR (case-insensitive); any one of them is enough, `*` matches all
+    #[serde(default)]
+    pub labels: Vec<String>,
+    /// Commits whose PR has any of these labels never match
+    #[serde(default)]
+    pub exclude_labels: Vec<String>,
+    /// Commits by these authors, by name or login, never match
+    #[serde(default)]
+    pub exclude_authors: Vec<String>,
+}
+
+impl CategoryRule {
+    fn for_types(key: &str, heading: &str, types: &[&str]) -> Self {
+        CategoryRule {
+            key: key.to_string(),
+            heading: heading.to_string(),
+            types: types.iter().map(|t| t.to_string()).collect(),
+            subject: None,
+            body: None,
+            author: None,
+            path: None,
+            labels: Vec::new(),
+            exclude_labels: Vec::new(),
+            exclude_authors: Vec::new(),
+        }
+    }
+
+    /// Whether the commit belongs in this category
+    pub fn matches(&self, commit: &CommitFacts) -> bool {
+        if self.types.is_empty()
+            && self.subject.is_none()
+            && self.body.is_none()
+            && self.author.is_none()
+            && self.path.is_none()
+            && self.labels.is_empty()
+        {
+            return false;
+        }
+
+        let regex_matches = |pattern: &Option<Pattern>, text: &str| match pattern {
+            Some(p) => p.is_match(text),
+            None => true,
+        };
+
+        if has_any_label(&self.exclude_labels, commit.labels)
+            || is_any_author(&self.exclude_authors, commit)
+        {
+            return false;
+        }
+
+        (self.types.is_empty()
+            || commit
+                .kind
+                .is_some_and(|k| self.types.iter().any(|t| t.eq_ignore_ascii_case(k))))
+            && regex_matches(&self.subject, commit.subject)
+            && regex_matches(&self.body, commit.body)
+            && regex_matches(&self.author, commit.author)
+            && (self.path.is_none() || commit.paths.iter().any(|p| regex_matches(&self.path, p)))
+            && (self.labels.is_empty() || has_any_label(&self.labels, commit.labels))
+    }
+}
+
+/// Whether `labels` contains any of `wanted`, ignoring case; `*` stands for any labels or none
+fn has_any_label(wanted: &[String], labels: &[String]) -> bool {
+    wanted.iter().any(|w| w == "*")
+        || labels
+            .iter()
+            .any(|label| wanted.iter().any(|w| w.eq_ignore_ascii_case(label)))
+}
+
+/// Whether the commit's author name or PR login is one of `authors`
+///
+/// Case is ignored, as is the `[bot]` suffix, so `dependabot` matches `dependabot[bot]`.
+fn is_any_author(authors: &[String], commit: &CommitFacts) -> bool {
+    let normalize = |name: &str| {
+        let name = name.trim().to_lowercase();
+        name.strip_suffix("[bot]").unwrap_or(&name).to_string()
+    };
+    let names: Vec<String> = [Some(commit.author), commit.login]
+        .into_iter()
+        .flatten()
+        .filter(|name| !name.is_empty())
+        .map(normalize)
+        .collect();
+    authors
+        .iter()
+        .any(|author| names.contains(&normalize(author)))
+}
+
+/// Which dependency updates are warned about, by how big they are
+///
+/// Updates to a pre-release are warned about at every level but `never`.
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum VersionWarning {
+    Patch,
+    Minor,
+    Major,
+    PreRelease,
+    Never,
+}
+
+impl VersionWarning {
+    /// Smallest kind of update warned about, `None` for `never`
+    pub fn kind(self) -> Option<UpdateKind> {
+        match self {
+            VersionWarning::Patch => Some(UpdateKind::Patch),
+            VersionWarning::Minor => Some(UpdateKind::Minor),
+            VersionWarning::Major => Some(UpdateKind::Major),
+            VersionWarning::PreRelease => Some(UpdateKind::PreRelease),
+            VersionWarning::Never => None,
+        }
+    }
+
+    /// Whether an update of this kind is warned about
+    pub fn applies_to(self, kind: UpdateKind) -> bool {
+        self.kind().is_some_and(|threshold| kind >= threshold)
+    }
+
+    /// What the warning calls the updates, e.g. "Major version changes"
+    pub fn description(self) -> &'static str {
+        match self {
+            VersionWarning::Major => "Major version changes",
+            VersionWarning::PreRelease => "Pre-release version changes",
+            _ => "Version changes",
+        }
+    }
+}
+
+/// Titles of the built-in sections
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Headings {
+    pub breaking: String,
+    pub dependencies: String,
+    pub other: String,
+    pub contributors: String,
+    /// Dependency changes found in lockfiles that no bot made
+    pub lockfiles: String,
+}
+
+impl Default for Headings {
+    fn default() -> Self {
+        Headings {
+            breaking: "⚠ Breaking changes".to_string(),
+            dependencies: "Dependencies updated by dependabot".to_string(),
+            other: "Other changes".to_string(),
+            contributors: "Contributors".to_string(),
+            lockfiles: "Dependencies changed in lockfiles".to_string(),
+        }
+    }
+}
+
+/// Defaults for command line options; flags set here can't be switched off from the command line
+#[derive(Debug, Clone, Default, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Defaults {
+    pub clipboard: bool,
+    pub include_pr_numbers: bool,
+    pub terse: bool,
+    pub no_sync: bool,
+    pub offline: bool,
+    /// Don't read or write the cache of PR lookups
+    pub no_cache: bool,
+    /// Fail when any PR lookup failed
+    pub strict: bool,
+    /// Output format, `markdown` or `json`
+    pub format: Option<String>,
+    /// Number of commits whose PRs are looked up at the same time
+    pub concurrency: Option<usize>,
+}
+
+impl Config {
+    /// Load `path` if given, otherwise `.releasenotes.toml` in `repo_root` when it exists
+    pub fn discover(explicit: Option<&Path>, repo_root: Option<&Path>) -> Result<Config> {
+        if let Some(path) = explicit {
+            return Config::load(path);
+        }
+        match repo_root.map(|root| root.join(CONFIG_FILE_NAME)) {
+            Some(path) if path.is_file() => Config::load(&path),
+            _ => Ok(Config::default()),
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<Config> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read config file {}", path.display()))?;
+        Config::parse(&content).with_context(|| format!("Invalid config file {}", path.display()))
+    }
+
+    pub fn parse(content: &str) -> Result<Config> {
+        let config: Config = toml::from_str(content)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    fn validate(&self) -> Result<()> {
+        for pattern in &self.skip {
+            if let Err(e) = &pattern.regex {
+                return Err(anyhow!(
+                    "Invalid skip pattern '{}': {}",
+                    pattern.as_str(),
+                    e
+                ));
+            }
+        }
+        for rule in &self.categories {
+            for pattern in [&rule.subject, &rule.body, &rule.author, &rule.path]
+                .into_iter()
+                .flatten()
+            {
+                if let Err(e) = &pattern.regex {
+                    return Err(anyhow!(
+                        "Invalid pattern '{}' in category '{}': {}",
+                        pattern.as_str(),
+                        rule.key,
+                        e
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn is_skipped(&self, subject: &str) -> bool {
+        self.skip.iter().any(|pattern| pattern.is_match(subject))
+    }
+
+    pub fn is_bot(&self, author: &str) -> bool {
+        let author = author.to_lowercase();
+        self.bot_authors
+            .iter()
+            .any(|bot| author.contains(&bot.to_lowercase()))
+    }
+
+    /// Whether the commit's PR labels or author say to leave it out
+    pub fn is_excluded(&self, commit: &CommitFacts) -> bool {
+        has_any_label(&self.skip_labels, commit.labels) || is_any_author(&self.skip_authors, commit)
+    }
+
+    /// Whether a PR label marks the commit as a breaking change
+    pub fn is_breaking_by_labels(&self, labels: &[String]) -> bool {
+        has_any_label(&self.breaking_labels, labels)
+    }
+
+    /// Key of the first category whose rule matches the commit
+    pub fn categorize(&self, commit: &CommitFacts) -> Option<&str> {
+        self.categories
+            .iter()
+            .find(|rule| rule.matches(commit))
+            .map(|rule| rule.key.as_str())
+    }
+
+    /// Whether any category looks at changed paths, which are expensive to compute
+    pub fn uses_paths(&self) -> bool {
+        self.categories.iter().any(|rule| rule.path.is_some())
+    }
+}
+
+/// A regex from the config file, compiled once when the file is read
+///
+/// One that doesn't compile keeps its error for [`Config::parse`] to report along with the
+/// setting it belongs to, and matches nothing.
+#[derive(Debug, Clone)]
+pub struct Pattern {
+    source: String,
+    regex: Result<Regex, regex::Error>,
+}
+
+impl Pattern {
+    pub fn new(source: &str) -> Self {
+        Pattern {
+            source: source.to_string(),
+            regex: Regex::new(source),
+        }
+    }
+
+    pub fn case_insensitive(source: &str) -> Self {
+        Pattern {
+            source: source.to_string(),
+            regex: RegexBuilder::new(source).case_insensitive(true).build(),
+        }
+    }
+
+    /// The pattern as written in the config file
+    pub fn as_str(&self) -> &str {
+        &self.source
+    }
+
+    pub fn is_match(&self, text: &str) -> bool {
+        self.regex.as_ref().is_ok_and(|re| re.is_match(text))
+    }
+}
+
+impl PartialEq for Pattern {
+    fn eq(&self, other: &Self) -> bool {
+        self.source == other.source
+    }
+}
+
+impl<'de> Deserialize<'de> for Pattern {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(|source| Pattern::new(&source))
+    }
+}
+
+fn case_insensitive_patterns<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<Pattern>, D::Error> {
+    let sources = Vec::<String>::deserialize(deserializer)?;
+    Ok(sources
+        .iter()
+        .map(|s| Pattern::case_insensitive(s))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_config() {
+        let config = Config::parse("").unwrap();
+        assert_eq!(config, Config::default());
+        assert!(config.is_skipped("Setting new snapshot version 1.0"));
+        assert!(config.is_bot("dependabot[bot]"));
+        let commit = |kind| CommitFacts {
+            kind: Some(kind),
+            subject: "add x",
+            author: "User",
+            ..Default::default()
+        };
+        assert_eq!(config.categorize(&commit("feat")), Some(CATEGORY_FEATURES));
+        assert_eq!(config.categorize(&commit("chore")), None);
+    }
+
+    #[test]
+    fn test_parse_full_config() {
+        let config = Config::parse(
+            r#"
+            branch = "develop"
+            remote = "upstream"
+            forge = "gitlab"
+            github_api_url = "https://github.example.com/api/v3"
+            skip = ["^wip"]
+            bot_authors = ["renovate", "dependabot"]
+            skip_labels = ["no-notes"]
+            skip_authors = ["octocat"]
+            version_warning = "minor"
+
+            [headings]
+            other = "Misc"
+
+            [[categories]]
+            key = "docs"
+            heading = "Documentation"
+            path = "^docs/"
+
+            [[categories]]
+            key = "security"
+            heading = "Security"
+            labels = ["security"]
+            exclude_authors = ["renovate"]
+
+            [[categories]]
+            key = "ci"
+            heading = "CI"
+            author = "^ci-bot$"
+            subject = "pipeline"
+
+            [defaults]
+            include_pr_numbers = true
+            format = "json"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.branch.as_deref(), Some("develop"));
+        assert_eq!(config.remote.as_deref(), Some("upstream"));
+        assert_eq!(config.forge, Some(ForgeKind::GitLab));
+        assert_eq!(
+            config.github_api_url.as_deref(),
+            Some("https://github.example.com/api/v3")
+        );
+        assert!(config.is_skipped("WIP: half done"));
+        assert!(!config.is_skipped("Setting new snapshot version 1.0"));
+        assert!(config.is_bot("renovate[bot]"));
+        assert!(config.is_breaking_by_labels(&["breaking".to_string()]));
+        assert_eq!(config.headings.other, "Misc");
+        assert_eq!(
+            config.headings.dependencies,
+            "Dependencies updated by dependabot"
+        );
+        assert!(config.defaults.include_pr_numbers);
+        assert_eq!(config.defaults.format.as_deref(), Some("json"));
+        assert!(config.uses_paths());
+        assert_eq!(config.version_warning, VersionWarning::Minor);
+        assert!(config.version_warning.applies_to(UpdateKind::PreRelease));
+        assert!(!config.version_warning.applies_to(UpdateKind::Patch));
+        assert!(!VersionWarning::Never.applies_to(UpdateKind::Major));
+
+        let paths = vec!["docs/index.md".to_string()];
+        let labels = vec!["bug".to_string(), "Security".to_string()];
+        let commit = CommitFacts {
+            subject: "x",
+            author: "User",
+            ..Default::default()
+        };
+        assert_eq!(
+            config.categorize(&CommitFacts {
+                paths: &paths,
+                ..commit
+            }),
+            Some("docs")
+        );
+        assert_eq!(
+            config.categorize(&CommitFacts {
+                kind: Some("feat"),
+                ..commit
+            }),
+            None
+        );
+        assert_eq!(
+            config.categorize(&CommitFacts {
+                labels: &labels,
+                ..commit
+            }),
+            Some("security")
+        );
+        assert_eq!(
+            config.categorize(&CommitFacts {
+                labels: &labels,
+                login: Some("renovate[bot]"),
+                ..commit
+            }),
+            None
+        );
+        let pipeline = CommitFacts {
+            subject: "fix pipeline",
+            ..commit
+        };
+        assert_eq!(
+            config.categorize(&CommitFacts {
+                author: "ci-bot",
+                ..pipeline
+            }),
+            Some("ci")
+        );
+        assert_eq!(config.categorize(&pipeline), None);
+
+        let no_notes = vec!["No-Notes".to_string()];
+        assert!(config.is_excluded(&CommitFacts {
+            labels: &no_notes,
+            ..commit
+        }));
+        assert!(config.is_excluded(&CommitFacts {
+            login: Some("OctoCat"),
+            ..commit
+        }));
+        let skip_changelog = vec!["skip-changelog".to_string()];
+        assert!(!config.is_excluded(&CommitFacts {
+            labels: &skip_changelog,
+            ..commit
+        }));
+    }
+
+    #[test]
+    fn test_invalid_pattern_rejected() {
+        let err = Config::parse("skip = [\"(\"]").unwrap_err();
+        assert!(err.to_string().contains("Invalid skip pattern"));
+
+        let err = Config::parse(
+            r#"
+            [[categories]]
+            key = "x"
+            heading = "X"
+            subject = "["
+            "#,
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("category 'x'"));
+    }
+
+    #[test]
+    fn test_unknown_key_rejected() {
+        assert!(Config::parse("brnach = \"main\"").is_err());
+    }
+}
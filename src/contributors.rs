@@ -0,0 +1,256 @@
+//! The people behind a release, for a section thanking them
+//!
+//! Commit authors and `Co-authored-by:` trailers are counted, both after `.mailmap` is applied,
+//! so someone committing under several names or addresses is listed once.
+
+use anyhow::Result;
+use gix::{ObjectId, Repository};
+use regex::Regex;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+
+use crate::config::{CommitFacts, Config};
+
+/// Someone who authored or co-authored a commit in the range
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Contributor {
+    pub name: String,
+    /// Left out of the output, the notes are usually public
+    #[serde(skip)]
+    pub email: String,
+    /// Forge login, when a PR or a GitHub noreply address tells it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub login: Option<String>,
+    /// Their first commit in the repository's history is in the range
+    pub first_time: bool,
+}
+
+impl Contributor {
+    /// Markdown line for the contributors section
+    pub fn line(&self) -> String {
+        let login = match &self.login {
+            Some(login) => format!(" (@{})", login),
+            None => String::new(),
+        };
+        let first_time = if self.first_time {
+            ", first contribution"
+        } else {
+            ""
+        };
+        format!("- {}{}{}", self.name, login, first_time)
+    }
+
+    /// Identifies the same person across commits: the email, or the name when there is none
+    fn key(&self) -> String {
+        identity_key(&self.name, &self.email)
+    }
+}
+
+fn identity_key(name: &str, email: &str) -> String {
+    if email.is_empty() {
+        name.to_lowercase()
+    } else {
+        email.to_lowercase()
+    }
+}
+
+/// Name and email of each `Co-authored-by:` trailer in a commit message body
+pub fn co_authors(body: &str) -> Vec<(String, String)> {
+    let re_co_author = Regex::new(r"(?im)^\s*co-authored-by:\s*(.*?)\s*<([^>]*)>\s*$").unwrap();
+    re_co_author
+        .captures_iter(body)
+        .map(|caps| (caps[1].to_string(), caps[2].trim().to_string()))
+        .filter(|(name, _)| !name.is_empty())
+        .collect()
+}
+
+/// Login in a GitHub noreply address, e.g. `octocat` in `123+octocat@users.noreply.github.com`
+fn noreply_login(email: &str) -> Option<String> {
+    let re_noreply =
+        Regex::new(r"(?i)^(?:[0-9]+\+)?([^@+]+)@users\.noreply\.github\.com$").unwrap();
+    re_noreply.captures(email).map(|caps| caps[1].to_string())
+}
+
+/// Authors and co-authors of a commit, mapped through `.mailmap`
+fn identities(
+    commit: &gix::Commit<'_>,
+    mailmap: &gix::mailmap::Snapshot,
+) -> Result<Vec<(String, String)>> {
+    let resolve = |name: &str, email: &str| {
+        let signature = mailmap.resolve_cow(gix::actor::SignatureRef {
+            name: name.into(),
+            email: email.into(),
+            time: "",
+        });
+        (
+            signature.name.to_string().trim().to_string(),
+            signature.email.to_string().trim().to_string(),
+        )
+    };
+    let author = commit.author()?;
+    let mut identities = vec![resolve(&author.name.to_string(), &author.email.to_string())];
+    let message = commit.message()?;
+    if let Some(body) = message.body() {
+        for (name, email) in co_authors(&body.to_string()) {
+            identities.push(resolve(&name, &email));
+        }
+    }
+    Ok(identities)
+}
+
+/// Contributors of the commits `commit_ids`, which are the range starting after `from`
+///
+/// `logins` maps commit hashes to the login of the PR's author. It is only used for commits
+/// that aren't merge commits, as a merge commit is authored by whoever merged the PR. Bots and
+/// the `skip_authors` aren't listed.
+pub fn contributors(
+    repo: &Repository,
+    commit_ids: &[ObjectId],
+    from: Option<ObjectId>,
+    logins: &HashMap<String, String>,
+    config: &Config,
+) -> Result<Vec<Contributor>> {
+    let mailmap = repo.open_mailmap();
+    let mut contributors: Vec<Contributor> = Vec::new();
+    for oid in commit_ids {
+        let commit = repo.find_commit(*oid)?;
+        let pr_login = logins
+            .get(&oid.to_string())
+            .filter(|_| commit.parent_ids().count() <= 1);
+        for (i, (name, email)) in identities(&commit, &mailmap)?.into_iter().enumerate() {
+            let login = match pr_login {
+                Some(login) if i == 0 => Some(login.clone()),
+                _ => noreply_login(&email),
+            };
+            let facts = CommitFacts {
+                author: &name,
+                login: login.as_deref(),
+                ..Default::default()
+            };
+            if config.is_bot(&name) || name.ends_with("[bot]") || config.is_excluded(&facts) {
+                continue;
+            }
+            let key = identity_key(&name, &email);
+            match contributors.iter_mut().find(|c| c.key() == key) {
+                Some(contributor) => {
+                    contributor.login = contributor.login.take().or(login);
+                }
+                None => contributors.push(Contributor {
+                    name,
+                    email,
+                    login,
+                    first_time: true,
+                }),
+            }
+        }
+    }
+
+    // Anyone who shows up in the history before the range has contributed before
+    if let Some(from) = from {
+        let mut newcomers: HashSet<String> = contributors.iter().map(|c| c.key()).collect();
+        for info in repo.rev_walk([from]).all()? {
+            if newcomers.is_empty() {
+                break;
+            }
+            let commit = repo.find_commit(info?.id)?;
+            for (name, email) in identities(&commit, &mailmap)? {
+                newcomers.remove(&identity_key(&name, &email));
+            }
+        }
+        for contributor in &mut contributors {
+            contributor.first_time = newcomers.contains(&contributor.key());
+        }
+    }
+
+    contributors.sort_by_key(|c| c.name.to_lowercase());
+    Ok(contributors)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{commit, git, init_repo};
+
+    #[test]
+    fn test_co_authors_and_logins() {
+        let body = "Pairing session.\n\nCo-authored-by: Jane Doe <jane@example.com>\n\
+                    co-authored-by: Octo Cat <123+octocat@users.noreply.github.com>";
+        assert_eq!(
+            co_authors(body),
+            vec![
+                ("Jane Doe".to_string(), "jane@example.com".to_string()),
+                (
+                    "Octo Cat".to_string(),
+                    "123+octocat@users.noreply.github.com".to_string()
+                ),
+            ]
+        );
+        assert_eq!(
+            noreply_login("123+octocat@users.noreply.github.com").as_deref(),
+            Some("octocat")
+        );
+        assert_eq!(
+            noreply_login("octocat@users.noreply.github.com").as_deref(),
+            Some("octocat")
+        );
+        assert_eq!(noreply_login("jane@example.com"), None);
+    }
+
+    #[test]
+    fn test_contributors_of_range() {
+        let dir = init_repo();
+        let path = dir.path();
+        let commit_as = |name: &str, email: &str, message: &str| {
+            git(
+                path,
+                &[
+                    "commit",
+                    "--allow-empty",
+                    "-q",
+                    "--author",
+                    &format!("{} <{}>", name, email),
+                    "-m",
+                    message,
+                ],
+            );
+            ObjectId::from_hex(git(path, &["rev-parse", "HEAD"]).as_bytes()).unwrap()
+        };
+
+        commit_as("Jane Doe", "jane@old.example.com", "Initial commit");
+        let from = commit(path, "Release 1.0");
+        std::fs::write(
+            path.join(".mailmap"),
+            "Jane Doe <jane@example.com> <jane@old.example.com>\n",
+        )
+        .unwrap();
+        let range = vec![
+            commit_as(
+                "Jane Doe",
+                "jane@example.com",
+                "Add export\n\nCo-authored-by: Sam Roe <sam@example.com>",
+            ),
+            commit_as("Max Poe", "max@example.com", "Fix typo"),
+            commit_as("dependabot[bot]", "bot@example.com", "Bump lib"),
+            commit_as("Max Poe", "max@example.com", "Fix another typo"),
+        ];
+        let logins = HashMap::from([(range[1].to_string(), "maxpoe".to_string())]);
+
+        let contributors = contributors(
+            &gix::open(path).unwrap(),
+            &range,
+            Some(from),
+            &logins,
+            &Config::default(),
+        )
+        .unwrap();
+        let lines: Vec<String> = contributors.iter().map(Contributor::line).collect();
+        assert_eq!(
+            lines,
+            vec![
+                "- Jane Doe",
+                "- Max Poe (@maxpoe), first contribution",
+                "- Sam Roe, first contribution",
+            ]
+        );
+    }
+}
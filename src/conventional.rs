@@ -0,0 +1,86 @@
+use regex::Regex;
+
+/// Category keys for the Conventional Commits types that get their own section by default
+pub const CATEGORY_FEATURES: &str = "features";
+pub const CATEGORY_FIXES: &str = "fixes";
+pub const CATEGORY_PERFORMANCE: &str = "performance";
+pub const CATEGORY_REFACTORING: &str = "refactoring";
+
+/// A commit subject parsed according to the Conventional Commits spec
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConventionalCommit {
+    pub kind: String,
+    pub scope: Option<String>,
+    pub breaking: bool,
+    pub description: String,
+}
+
+/// Parse `type(scope)!: description`, with a `BREAKING CHANGE:` footer in the body also marking it breaking
+pub fn parse(subject: &str, body: &str) -> Option<ConventionalCommit> {
+    let re_subject = Regex::new(r"^([A-Za-z]+)(?:\(([^()]*)\))?(!)?: +(\S.*)$").unwrap();
+    let caps = re_subject.captures(subject.trim())?;
+
+    let breaking_footer = body.lines().any(|line| {
+        let line = line.trim_start();
+        line.starts_with("BREAKING CHANGE:") || line.starts_with("BREAKING-CHANGE:")
+    });
+
+    Some(ConventionalCommit {
+        kind: caps.get(1).unwrap().as_str().to_lowercase(),
+        scope: caps
+            .get(2)
+            .map(|m| m.as_str().trim().to_string())
+            .filter(|s| !s.is_empty()),
+        breaking: caps.get(3).is_some() || breaking_footer,
+        description: caps.get(4).unwrap().as_str().trim().to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_type_and_description() {
+        let cc = parse("feat: add login page", "").unwrap();
+        assert_eq!(cc.kind, "feat");
+        assert_eq!(cc.scope, None);
+        assert!(!cc.breaking);
+        assert_eq!(cc.description, "add login page");
+    }
+
+    #[test]
+    fn test_parse_scope_and_breaking_marker() {
+        let cc = parse("fix(api)!: drop v1 endpoints", "").unwrap();
+        assert_eq!(cc.kind, "fix");
+        assert_eq!(cc.scope.as_deref(), Some("api"));
+        assert!(cc.breaking);
+    }
+
+    #[test]
+    fn test_parse_breaking_footer() {
+        let body = "Some details\n\nBREAKING CHANGE: config format changed";
+        let cc = parse("refactor(config): new loader", body).unwrap();
+        assert!(cc.breaking);
+        assert_eq!(cc.kind, "refactor");
+    }
+
+    #[test]
+    fn test_parse_uncategorized_type() {
+        let cc = parse("chore: tidy up", "").unwrap();
+        assert_eq!(cc.kind, "chore");
+        let commit = crate::config::CommitFacts {
+            kind: Some(&cc.kind),
+            subject: "chore: tidy up",
+            ..Default::default()
+        };
+        assert_eq!(crate::Config::default().categorize(&commit), None);
+    }
+
+    #[test]
+    fn test_parse_non_conventional() {
+        assert_eq!(parse("Fix bug", ""), None);
+        assert_eq!(parse("Merge pull request #123 from foo", ""), None);
+        assert_eq!(parse("feat:missing space", ""), None);
+    }
+}
@@ -0,0 +1,167 @@
+//! Which package ecosystem and manifest directory a dependency update belongs to
+//!
+//! Dependabot names both in its PR titles, e.g. "Bump the npm_and_yarn group in /frontend", and
+//! labels its PRs by language; Renovate says e.g. "update rust crate serde".
+
+use regex::Regex;
+
+/// Ecosystems by Dependabot's `package-ecosystem` key, with their title and other names for them:
+/// group names, Dependabot's labels and Renovate's words
+const ECOSYSTEMS: [(&str, &str, &[&str]); 12] = [
+    ("cargo", "Cargo", &["rust", "rust crate", "crate"]),
+    (
+        "npm",
+        "npm",
+        &["npm_and_yarn", "yarn", "pnpm", "javascript"],
+    ),
+    (
+        "github-actions",
+        "GitHub Actions",
+        &["github_actions", "action"],
+    ),
+    (
+        "docker",
+        "Docker",
+        &["docker tag", "docker digest", "docker image"],
+    ),
+    ("maven", "Maven", &["java"]),
+    ("gradle", "Gradle", &[]),
+    ("gomod", "Go modules", &["go", "go module", "module"]),
+    ("pip", "pip", &["python", "pipenv", "poetry"]),
+    ("bundler", "Bundler", &["ruby", "gem"]),
+    ("composer", "Composer", &["php"]),
+    ("nuget", "NuGet", &[".net"]),
+    ("terraform", "Terraform", &[]),
+];
+
+/// Where the updates of a commit were made
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Source {
+    /// Dependabot's `package-ecosystem` key, e.g. `cargo` or `github-actions`
+    pub ecosystem: Option<String>,
+    /// Manifest directory, e.g. `/frontend`; `None` for the root of the repository
+    pub directory: Option<String>,
+}
+
+/// Ecosystem key for one of its names, case-insensitive
+fn ecosystem_named(name: &str) -> Option<&'static str> {
+    let name = name.trim().to_lowercase();
+    ECOSYSTEMS
+        .iter()
+        .find(|(key, _, aliases)| *key == name || aliases.contains(&name.as_str()))
+        .map(|(key, _, _)| *key)
+}
+
+impl Source {
+    /// Source named in commit subjects or PR titles, or by the PR's labels
+    pub fn detect(titles: &[&str], labels: &[String]) -> Source {
+        let re_group = Regex::new(r"(?i)\bBumps? the (\S+) group\b").unwrap();
+        let re_renovate = Regex::new(
+            r"(?i)\bupdate (rust crate|docker (?:tag|digest|image)|go module|module|gem)\b",
+        )
+        .unwrap();
+        let re_action = Regex::new(r"(?i)\bupdate \S+ action\b").unwrap();
+        let re_directory = Regex::new(r"\bin (/[^\s:,]*)").unwrap();
+
+        let from_titles = titles.iter().find_map(|title| {
+            if let Some(caps) = re_group.captures(title) {
+                return ecosystem_named(&caps[1]);
+            }
+            if let Some(caps) = re_renovate.captures(title) {
+                return ecosystem_named(&caps[1]);
+            }
+            re_action.is_match(title).then_some("github-actions")
+        });
+        let ecosystem = from_titles.or_else(|| labels.iter().find_map(|l| ecosystem_named(l)));
+
+        let directory = titles
+            .iter()
+            .find_map(|title| re_directory.captures(title))
+            .map(|caps| caps[1].trim_end_matches('/').to_string())
+            .filter(|directory| !directory.is_empty());
+
+        Source {
+            ecosystem: ecosystem.map(str::to_string),
+            directory,
+        }
+    }
+
+    /// Sub-section title, e.g. "npm in /frontend"
+    pub fn title(&self) -> String {
+        let ecosystem = self.ecosystem.as_deref().map(|key| {
+            ECOSYSTEMS
+                .iter()
+                .find(|(k, _, _)| *k == key)
+                .map_or(key, |(_, title, _)| *title)
+        });
+        match (ecosystem, &self.directory) {
+            (Some(ecosystem), Some(directory)) => format!("{} in {}", ecosystem, directory),
+            (Some(ecosystem), None) => ecosystem.to_string(),
+            (None, Some(directory)) => format!("Dependencies in {}", directory),
+            (None, None) => "Other dependencies".to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn source(ecosystem: Option<&str>, directory: Option<&str>) -> Source {
+        Source {
+            ecosystem: ecosystem.map(str::to_string),
+            directory: directory.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_detect_source() {
+        let labels =
+            |names: &[&str]| -> Vec<String> { names.iter().map(|n| n.to_string()).collect() };
+        assert_eq!(
+            Source::detect(
+                &["Bump the npm_and_yarn group in /frontend with 3 updates"],
+                &[]
+            ),
+            source(Some("npm"), Some("/frontend"))
+        );
+        assert_eq!(
+            Source::detect(
+                &["Bump serde from 1.0.1 to 1.0.2 in /backend"],
+                &labels(&["dependencies", "rust"])
+            ),
+            source(Some("cargo"), Some("/backend"))
+        );
+        assert_eq!(
+            Source::detect(&["Bump the github-actions group in / with 2 updates"], &[]),
+            source(Some("github-actions"), None)
+        );
+        assert_eq!(
+            Source::detect(&["chore(deps): update actions/checkout action to v4"], &[]),
+            source(Some("github-actions"), None)
+        );
+        assert_eq!(
+            Source::detect(&["fix(deps): update rust crate serde to 1.0.200"], &[]),
+            source(Some("cargo"), None)
+        );
+        // Group names are free to choose, only ecosystem names say something
+        assert_eq!(
+            Source::detect(&["Bump the minor-updates group across 2 directories"], &[]),
+            source(None, None)
+        );
+    }
+
+    #[test]
+    fn test_source_titles() {
+        assert_eq!(
+            source(Some("github-actions"), None).title(),
+            "GitHub Actions"
+        );
+        assert_eq!(
+            source(Some("npm"), Some("/frontend")).title(),
+            "npm in /frontend"
+        );
+        assert_eq!(source(None, Some("/docs")).title(), "Dependencies in /docs");
+        assert_eq!(Source::default().title(), "Other dependencies");
+    }
+}
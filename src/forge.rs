@@ -0,0 +1,439 @@
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use reqwest::header::HeaderMap;
+use reqwest::StatusCode;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::ratelimit::RateLimiter;
+use crate::warnings::{ForgeError, ProblemKind, Warnings};
+
+mod bitbucket;
+mod gitea;
+mod github;
+mod gitlab;
+
+pub use bitbucket::Bitbucket;
+pub use gitea::Gitea;
+pub use github::GitHub;
+pub use gitlab::GitLab;
+
+/// A pull request (GitHub, Gitea, Bitbucket) or merge request (GitLab)
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ChangeRequest {
+    pub number: u64,
+    pub title: String,
+    pub body: Option<String>,
+    pub labels: Vec<String>,
+    /// Login or username of whoever opened it
+    pub author: Option<String>,
+}
+
+/// Outcome of [`Forge::find_change_requests`]
+#[derive(Debug, Default)]
+pub struct BulkLookup {
+    /// Change request of each commit looked up, `None` for commits that have none
+    pub found: HashMap<String, Option<u64>>,
+    /// What stopped the lookup before it covered all commits, if anything did
+    pub error: Option<anyhow::Error>,
+}
+
+/// Outcome of fetching a change request that may be known already, see
+/// [`Forge::change_request_if_modified`]
+#[derive(Debug, Clone, PartialEq)]
+pub enum Revalidated {
+    /// The copy with the given ETag is still current
+    NotModified,
+    Modified {
+        change_request: Option<ChangeRequest>,
+        /// ETag to revalidate this copy with later, if the forge sent one
+        etag: Option<String>,
+    },
+}
+
+/// The hosting service behind the remote, used to look up the change requests commits came from
+#[async_trait]
+pub trait Forge: Send + Sync {
+    /// Number of the change request that introduced the commit, if any
+    async fn find_change_request(&self, sha: &str) -> Result<Option<u64>>;
+
+    /// Change requests of many commits at once, for forges that can look them up in bulk
+    ///
+    /// Commits left out of the result weren't looked up; by default none are.
+    async fn find_change_requests(&self, _shas: &[String]) -> BulkLookup {
+        BulkLookup::default()
+    }
+
+    /// Details of a change request, `None` when there is no such change request
+    async fn change_request(&self, number: u64) -> Result<Option<ChangeRequest>>;
+
+    /// Details of a change request, unless it hasn't changed since the copy tagged `etag`
+    ///
+    /// By default the change request is fetched again without an ETag.
+    async fn change_request_if_modified(
+        &self,
+        number: u64,
+        _etag: Option<&str>,
+    ) -> Result<Revalidated> {
+        Ok(Revalidated::Modified {
+            change_request: self.change_request(number).await?,
+            etag: None,
+        })
+    }
+}
+
+/// A forge with the change requests of a set of commits looked up in bulk beforehand
+///
+/// Commits the bulk lookup didn't cover, e.g. the ones after a batch that failed, fall back to
+/// [`Forge::find_change_request`] one at a time. A failed bulk lookup is still reported, as
+/// the same problem usually makes the single lookups fail too.
+pub struct Prefetched<'a> {
+    forge: &'a dyn Forge,
+    found: HashMap<String, Option<u64>>,
+}
+
+impl<'a> Prefetched<'a> {
+    pub async fn new(forge: &'a dyn Forge, shas: &[String], warnings: &Warnings) -> Prefetched<'a> {
+        let BulkLookup { found, error } = forge.find_change_requests(shas).await;
+        if let Some(e) = error {
+            warnings.lookup_failed(format!("{} commits in bulk", shas.len()), &e);
+        }
+        Prefetched { forge, found }
+    }
+
+    /// Number of commits the bulk lookup covered
+    pub fn len(&self) -> usize {
+        self.found.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.found.is_empty()
+    }
+}
+
+#[async_trait]
+impl Forge for Prefetched<'_> {
+    async fn find_change_request(&self, sha: &str) -> Result<Option<u64>> {
+        match self.found.get(sha) {
+            Some(found) => Ok(*found),
+            None => self.forge.find_change_request(sha).await,
+        }
+    }
+
+    async fn change_request(&self, number: u64) -> Result<Option<ChangeRequest>> {
+        self.forge.change_request(number).await
+    }
+
+    async fn change_request_if_modified(
+        &self,
+        number: u64,
+        etag: Option<&str>,
+    ) -> Result<Revalidated> {
+        self.forge.change_request_if_modified(number, etag).await
+    }
+}
+
+/// GET `url` from `forge`, retrying while `limiter` says it is rate limited
+///
+/// `prepare` adds authentication and any other headers to each attempt. Returns the status,
+/// headers and body of the response.
+async fn get_with_retries(
+    client: &reqwest::Client,
+    limiter: &RateLimiter,
+    forge: &str,
+    url: &str,
+    prepare: impl Fn(reqwest::RequestBuilder) -> reqwest::RequestBuilder,
+) -> Result<(StatusCode, HeaderMap, String)> {
+    let mut attempt = 0;
+    loop {
+        limiter.wait().await;
+        let response = prepare(client.get(url))
+            .send()
+            .await
+            .map_err(|e| network_error(forge, url, e))?;
+        let status = response.status();
+        let headers = response.headers().clone();
+        let text = response
+            .text()
+            .await
+            .map_err(|e| network_error(forge, url, e))?;
+        if limiter
+            .check(status.as_u16(), &headers, &text, attempt)?
+            .is_some()
+        {
+            attempt += 1;
+            continue;
+        }
+        return Ok((status, headers, text));
+    }
+}
+
+/// Error for a request to `forge` that got no response
+fn network_error(forge: &str, url: &str, err: impl std::fmt::Display) -> anyhow::Error {
+    ForgeError::new(
+        ProblemKind::Network,
+        format!("{} request failed: {}: {}", forge, url, err),
+    )
+    .into()
+}
+
+/// Deserialize a response body from `forge`, `None` on 404
+///
+/// Errors include the message the forge sent along, e.g. GitHub's "Bad credentials".
+fn parse_response<T: DeserializeOwned>(
+    forge: &str,
+    url: &str,
+    status: StatusCode,
+    text: &str,
+) -> Result<Option<T>> {
+    if status == StatusCode::NOT_FOUND {
+        return Ok(None);
+    }
+    if !status.is_success() {
+        // Any 403 that was about rate limits has been retried already
+        let kind = match status {
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => ProblemKind::InvalidToken,
+            _ => ProblemKind::Api,
+        };
+        // GitHub, GitLab and Gitea send `message`, Bitbucket `error.message`
+        let message = serde_json::from_str::<serde_json::Value>(text)
+            .ok()
+            .and_then(|error| {
+                [&error["message"], &error["error"]["message"]]
+                    .into_iter()
+                    .find_map(|message| message.as_str().map(str::to_string))
+            })
+            .map(|message| format!(": {}", message))
+            .unwrap_or_default();
+        return Err(ForgeError::new(
+            kind,
+            format!(
+                "{} request failed: {} (HTTP {}){}",
+                forge,
+                url,
+                status.as_u16(),
+                message
+            ),
+        )
+        .into());
+    }
+    let value = serde_json::from_str(text)
+        .with_context(|| format!("Unexpected response from {} for {}", forge, url))?;
+    Ok(Some(value))
+}
+
+/// Supported forges, as written in the config file
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ForgeKind {
+    GitHub,
+    GitLab,
+    /// Gitea and its fork Forgejo, which Codeberg runs
+    Gitea,
+    /// Bitbucket Cloud
+    Bitbucket,
+}
+
+impl ForgeKind {
+    /// Guess the forge from the remote's host name
+    pub fn detect(host: &str) -> Option<ForgeKind> {
+        let host = host.to_lowercase();
+        if host.contains("github") {
+            Some(ForgeKind::GitHub)
+        } else if host.contains("gitlab") {
+            Some(ForgeKind::GitLab)
+        } else if ["gitea", "forgejo", "codeberg"]
+            .iter()
+            .any(|name| host.contains(name))
+        {
+            Some(ForgeKind::Gitea)
+        } else if host.contains("bitbucket") {
+            Some(ForgeKind::Bitbucket)
+        } else {
+            None
+        }
+    }
+
+    /// What comes before the number in a reference to a change request, `!` for a GitLab merge
+    /// request, as `#` refers to an issue there
+    pub fn reference_sigil(self) -> char {
+        match self {
+            ForgeKind::GitLab => '!',
+            ForgeKind::GitHub | ForgeKind::Gitea | ForgeKind::Bitbucket => '#',
+        }
+    }
+
+    /// URL that `compare/<from>...<to>` is appended to for links between two tags
+    ///
+    /// `None` for Bitbucket, whose compare pages take the tags the other way round.
+    pub fn compare_base_url(&self, remote: &RemoteRepo) -> Option<String> {
+        match self {
+            ForgeKind::GitHub | ForgeKind::Gitea => Some(remote.web_url()),
+            ForgeKind::GitLab => Some(format!("{}/-", remote.web_url())),
+            ForgeKind::Bitbucket => None,
+        }
+    }
+}
+
+/// Host and repository path taken from a remote URL
+#[derive(Debug, Clone, PartialEq)]
+pub struct RemoteRepo {
+    pub host: String,
+    /// `owner/repo` on GitHub, possibly with nested groups on GitLab
+    pub path: String,
+}
+
+impl RemoteRepo {
+    /// Parse `https://host/owner/repo.git`, `ssh://git@host:22/owner/repo` or `git@host:owner/repo`
+    ///
+    /// Local paths and `file://` URLs have no host and give `None`.
+    pub fn parse(url: &str) -> Option<RemoteRepo> {
+        let url = url.trim();
+        let (host, path) = if let Some((_, rest)) = url.split_once("://") {
+            let (authority, path) = rest.split_once('/')?;
+            let host = authority.rsplit('@').next()?;
+            (host.split(':').next()?, path)
+        } else {
+            // scp-like syntax, which needs a colon before the first slash
+            let (authority, path) = url.split_once(':')?;
+            if authority.contains('/') {
+                return None;
+            }
+            (authority.rsplit('@').next()?, path)
+        };
+
+        let path = path.trim_matches('/');
+        let path = path.strip_suffix(".git").unwrap_or(path);
+        if host.is_empty() || !path.contains('/') {
+            return None;
+        }
+        Some(RemoteRepo {
+            host: host.to_lowercase(),
+            path: path.to_string(),
+        })
+    }
+
+    /// Owner (or group) and repository name
+    pub fn owner_and_name(&self) -> (&str, &str) {
+        self.path.rsplit_once('/').unwrap_or(("", &self.path))
+    }
+
+    pub fn web_url(&self) -> String {
+        format!("https://{}/{}", self.host, self.path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_remote_urls() {
+        let expected = |host: &str, path: &str| {
+            Some(RemoteRepo {
+                host: host.to_string(),
+                path: path.to_string(),
+            })
+        };
+        assert_eq!(
+            RemoteRepo::parse("git@github.com:owner/repo.git"),
+            expected("github.com", "owner/repo")
+        );
+        assert_eq!(
+            RemoteRepo::parse("https://github.com/owner/repo"),
+            expected("github.com", "owner/repo")
+        );
+        assert_eq!(
+            RemoteRepo::parse("https://token@gitlab.example.com/group/sub/project.git"),
+            expected("gitlab.example.com", "group/sub/project")
+        );
+        assert_eq!(
+            RemoteRepo::parse("ssh://git@gitlab.example.com:2222/group/project.git"),
+            expected("gitlab.example.com", "group/project")
+        );
+        assert_eq!(RemoteRepo::parse("/srv/git/repo.git"), None);
+        assert_eq!(RemoteRepo::parse("file:///srv/git/repo.git"), None);
+        assert_eq!(RemoteRepo::parse("../repo"), None);
+    }
+
+    /// Forge that looks up "bulk-*" commits in bulk and counts single lookups
+    struct CountingForge(std::sync::atomic::AtomicUsize);
+
+    #[async_trait]
+    impl Forge for CountingForge {
+        async fn find_change_request(&self, _sha: &str) -> Result<Option<u64>> {
+            self.0.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            Ok(Some(1))
+        }
+
+        async fn find_change_requests(&self, shas: &[String]) -> BulkLookup {
+            BulkLookup {
+                found: shas
+                    .iter()
+                    .filter(|sha| sha.starts_with("bulk-"))
+                    .map(|sha| (sha.clone(), None))
+                    .collect(),
+                error: None,
+            }
+        }
+
+        async fn change_request(&self, _number: u64) -> Result<Option<ChangeRequest>> {
+            Ok(None)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_prefetched_falls_back_to_single_lookups() {
+        let forge = CountingForge(Default::default());
+        let shas = vec!["bulk-1".to_string(), "single-1".to_string()];
+        let warnings = Warnings::default();
+        let prefetched = Prefetched::new(&forge, &shas, &warnings).await;
+        assert!(warnings.is_empty());
+        assert_eq!(prefetched.len(), 1);
+
+        assert_eq!(
+            prefetched.find_change_request("bulk-1").await.unwrap(),
+            None
+        );
+        assert_eq!(forge.0.load(std::sync::atomic::Ordering::SeqCst), 0);
+        assert_eq!(
+            prefetched.find_change_request("single-1").await.unwrap(),
+            Some(1)
+        );
+        assert_eq!(forge.0.load(std::sync::atomic::Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_owner_and_name() {
+        let remote = RemoteRepo::parse("git@gitlab.com:group/sub/project.git").unwrap();
+        assert_eq!(remote.owner_and_name(), ("group/sub", "project"));
+    }
+
+    #[test]
+    fn test_detect_forge() {
+        assert_eq!(ForgeKind::detect("github.com"), Some(ForgeKind::GitHub));
+        assert_eq!(
+            ForgeKind::detect("gitlab.example.com"),
+            Some(ForgeKind::GitLab)
+        );
+        assert_eq!(ForgeKind::detect("codeberg.org"), Some(ForgeKind::Gitea));
+        assert_eq!(
+            ForgeKind::detect("gitea.example.com"),
+            Some(ForgeKind::Gitea)
+        );
+        assert_eq!(
+            ForgeKind::detect("bitbucket.org"),
+            Some(ForgeKind::Bitbucket)
+        );
+        assert_eq!(ForgeKind::detect("git.example.com"), None);
+
+        let remote = RemoteRepo::parse("git@gitlab.com:group/project.git").unwrap();
+        assert_eq!(
+            ForgeKind::GitLab.compare_base_url(&remote).as_deref(),
+            Some("https://gitlab.com/group/project/-")
+        );
+        let remote = RemoteRepo::parse("git@bitbucket.org:team/repo.git").unwrap();
+        assert_eq!(ForgeKind::Bitbucket.compare_base_url(&remote), None);
+    }
+}
@@ -0,0 +1,216 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use reqwest::header::AUTHORIZATION;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+
+use super::{get_with_retries, parse_response, ChangeRequest, Forge};
+use crate::ratelimit::RateLimiter;
+
+/// Pull requests on Bitbucket Cloud
+pub struct Bitbucket {
+    client: reqwest::Client,
+    /// API root, e.g. `https://api.bitbucket.org/2.0`
+    api_url: String,
+    /// Workspace and repository slug, e.g. `workspace/repo`
+    repo: String,
+    token: Option<String>,
+    limiter: RateLimiter,
+}
+
+/// One page of a paginated listing
+#[derive(Deserialize)]
+struct Page<T> {
+    #[serde(default = "Vec::new")]
+    values: Vec<T>,
+}
+
+#[derive(Deserialize)]
+struct PullRequest {
+    id: u64,
+    title: String,
+    description: Option<String>,
+    state: String,
+    author: Option<User>,
+}
+
+impl From<PullRequest> for ChangeRequest {
+    fn from(pr: PullRequest) -> Self {
+        ChangeRequest {
+            number: pr.id,
+            title: pr.title,
+            body: pr.description.filter(|body| !body.is_empty()),
+            // Bitbucket pull requests have no labels
+            labels: Vec::new(),
+            author: pr
+                .author
+                .and_then(|user| user.nickname.or(user.display_name)),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct User {
+    nickname: Option<String>,
+    display_name: Option<String>,
+}
+
+impl Bitbucket {
+    /// `token` is sent as a bearer token, e.g. a repository access token; public repositories
+    /// can be read without one
+    pub fn new(api_url: &str, repo: &str, token: Option<String>) -> Result<Self> {
+        let client = reqwest::Client::builder()
+            .user_agent(concat!("git-releasenotes/", env!("CARGO_PKG_VERSION")))
+            .build()?;
+        Ok(Bitbucket {
+            client,
+            api_url: api_url.trim_end_matches('/').to_string(),
+            repo: repo.to_string(),
+            token,
+            limiter: RateLimiter::default(),
+        })
+    }
+
+    /// API root for repositories on `host`, `None` for self-hosted Bitbucket Data Center,
+    /// which has a different API
+    pub fn api_url_for_host(host: &str) -> Option<String> {
+        match host {
+            "bitbucket.org" | "www.bitbucket.org" | "altssh.bitbucket.org" => {
+                Some("https://api.bitbucket.org/2.0".to_string())
+            }
+            _ => None,
+        }
+    }
+
+    /// GET a repository endpoint, retrying while rate limited, `None` on 404
+    async fn get<T: DeserializeOwned>(&self, endpoint: &str) -> Result<Option<T>> {
+        let url = format!("{}/repositories/{}/{}", self.api_url, self.repo, endpoint);
+        let (status, _, text) = get_with_retries(
+            &self.client,
+            &self.limiter,
+            "Bitbucket",
+            &url,
+            |request| match &self.token {
+                Some(token) => request.header(AUTHORIZATION, format!("Bearer {}", token)),
+                None => request,
+            },
+        )
+        .await?;
+        parse_response("Bitbucket", &url, status, &text)
+    }
+}
+
+#[async_trait]
+impl Forge for Bitbucket {
+    async fn find_change_request(&self, sha: &str) -> Result<Option<u64>> {
+        // While Bitbucket is still indexing the repository it answers 202 without any values
+        let page: Option<Page<PullRequest>> =
+            self.get(&format!("commit/{}/pullrequests", sha)).await?;
+        // Like on GitLab, only the merged pull request brought the commit in
+        Ok(page
+            .into_iter()
+            .flat_map(|page| page.values)
+            .find(|pr| pr.state == "MERGED")
+            .map(|pr| pr.id))
+    }
+
+    async fn change_request(&self, number: u64) -> Result<Option<ChangeRequest>> {
+        let pr: Option<PullRequest> = self.get(&format!("pullrequests/{}", number)).await?;
+        Ok(pr.map(ChangeRequest::from))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use wiremock::matchers::{header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn pull_request_json(id: u64, state: &str) -> serde_json::Value {
+        json!({
+            "type": "pullrequest",
+            "id": id,
+            "title": format!("PR {}", id),
+            "description": "Updates `serde` from 1.0.1 to 1.0.2",
+            "state": state,
+            "author": { "display_name": "Renovate Bot", "nickname": "renovate-bot" }
+        })
+    }
+
+    #[tokio::test]
+    async fn test_find_pull_request_by_sha() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path(
+                "/2.0/repositories/team/repo/commit/abc123/pullrequests",
+            ))
+            .and(header("authorization", "Bearer secret"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "pagelen": 10,
+                "page": 1,
+                "values": [pull_request_json(3, "DECLINED"), pull_request_json(5, "MERGED")]
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path(
+                "/2.0/repositories/team/repo/commit/def456/pullrequests",
+            ))
+            .respond_with(ResponseTemplate::new(202).set_body_json(json!({
+                "type": "error",
+                "error": { "message": "Indexing in progress" }
+            })))
+            .mount(&server)
+            .await;
+
+        let forge = Bitbucket::new(
+            &format!("{}/2.0", server.uri()),
+            "team/repo",
+            Some("secret".to_string()),
+        )
+        .unwrap();
+        assert_eq!(forge.find_change_request("abc123").await.unwrap(), Some(5));
+        assert_eq!(forge.find_change_request("def456").await.unwrap(), None);
+        // Unknown commits give a 404
+        assert_eq!(forge.find_change_request("fff000").await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_pull_request_details() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/2.0/repositories/team/repo/pullrequests/5"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(pull_request_json(5, "MERGED")))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/2.0/repositories/team/repo/pullrequests/6"))
+            .respond_with(ResponseTemplate::new(403))
+            .mount(&server)
+            .await;
+
+        let forge = Bitbucket::new(&format!("{}/2.0/", server.uri()), "team/repo", None).unwrap();
+        assert_eq!(
+            forge.change_request(5).await.unwrap(),
+            Some(ChangeRequest {
+                number: 5,
+                title: "PR 5".to_string(),
+                body: Some("Updates `serde` from 1.0.1 to 1.0.2".to_string()),
+                labels: vec![],
+                author: Some("renovate-bot".to_string()),
+            })
+        );
+        assert_eq!(forge.change_request(7).await.unwrap(), None);
+        assert!(forge.change_request(6).await.is_err());
+    }
+
+    #[test]
+    fn test_api_url_for_host() {
+        assert_eq!(
+            Bitbucket::api_url_for_host("bitbucket.org").as_deref(),
+            Some("https://api.bitbucket.org/2.0")
+        );
+        assert_eq!(Bitbucket::api_url_for_host("bitbucket.example.com"), None);
+    }
+}
@@ -0,0 +1,183 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use reqwest::header::AUTHORIZATION;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+
+use super::{get_with_retries, parse_response, ChangeRequest, Forge};
+use crate::ratelimit::RateLimiter;
+
+/// Pull requests on a Gitea or Forgejo instance, such as Codeberg
+pub struct Gitea {
+    client: reqwest::Client,
+    /// API root, e.g. `https://codeberg.org/api/v1`
+    api_url: String,
+    /// Repository path, e.g. `owner/repo`
+    repo: String,
+    token: Option<String>,
+    limiter: RateLimiter,
+}
+
+#[derive(Deserialize)]
+struct PullRequest {
+    number: u64,
+    title: String,
+    body: Option<String>,
+    #[serde(default)]
+    labels: Vec<Label>,
+    #[serde(default)]
+    merged: bool,
+    user: Option<User>,
+}
+
+impl From<PullRequest> for ChangeRequest {
+    fn from(pr: PullRequest) -> Self {
+        ChangeRequest {
+            number: pr.number,
+            title: pr.title,
+            body: pr.body.filter(|body| !body.is_empty()),
+            labels: pr.labels.into_iter().map(|label| label.name).collect(),
+            author: pr.user.map(|user| user.login),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct Label {
+    name: String,
+}
+
+#[derive(Deserialize)]
+struct User {
+    login: String,
+}
+
+impl Gitea {
+    /// `token` is sent as an access token; public repositories can be read without one
+    pub fn new(api_url: &str, repo: &str, token: Option<String>) -> Result<Self> {
+        let client = reqwest::Client::builder()
+            .user_agent(concat!("git-releasenotes/", env!("CARGO_PKG_VERSION")))
+            .build()?;
+        Ok(Gitea {
+            client,
+            api_url: api_url.trim_end_matches('/').to_string(),
+            repo: repo.to_string(),
+            token,
+            limiter: RateLimiter::default(),
+        })
+    }
+
+    /// API root of the Gitea instance hosting `host`
+    pub fn api_url_for_host(host: &str) -> String {
+        format!("https://{}/api/v1", host)
+    }
+
+    /// GET a repository endpoint, retrying while rate limited, `None` on 404
+    async fn get<T: DeserializeOwned>(&self, endpoint: &str) -> Result<Option<T>> {
+        let url = format!("{}/repos/{}/{}", self.api_url, self.repo, endpoint);
+        let (status, _, text) = get_with_retries(
+            &self.client,
+            &self.limiter,
+            "Gitea",
+            &url,
+            |request| match &self.token {
+                Some(token) => request.header(AUTHORIZATION, format!("token {}", token)),
+                None => request,
+            },
+        )
+        .await?;
+        parse_response("Gitea", &url, status, &text)
+    }
+}
+
+#[async_trait]
+impl Forge for Gitea {
+    async fn find_change_request(&self, sha: &str) -> Result<Option<u64>> {
+        // Gitea answers with the pull request that merged the commit, or 404
+        let pr: Option<PullRequest> = self.get(&format!("commits/{}/pull", sha)).await?;
+        Ok(pr.filter(|pr| pr.merged).map(|pr| pr.number))
+    }
+
+    async fn change_request(&self, number: u64) -> Result<Option<ChangeRequest>> {
+        let pr: Option<PullRequest> = self.get(&format!("pulls/{}", number)).await?;
+        Ok(pr.map(ChangeRequest::from))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use wiremock::matchers::{header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn pull_request_json(number: u64, merged: bool) -> serde_json::Value {
+        json!({
+            "id": 1000 + number,
+            "number": number,
+            "title": format!("PR {}", number),
+            "body": "Updates `serde` from 1.0.1 to 1.0.2",
+            "labels": [{ "id": 1, "name": "dependencies", "color": "ee0701" }],
+            "state": "closed",
+            "merged": merged,
+            "user": { "id": 1, "login": "renovate-bot" }
+        })
+    }
+
+    #[tokio::test]
+    async fn test_find_pull_request_by_sha() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/repos/owner/repo/commits/abc123/pull"))
+            .and(header("authorization", "token secret"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(pull_request_json(5, true)))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/repos/owner/repo/commits/def456/pull"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(pull_request_json(4, false)))
+            .mount(&server)
+            .await;
+
+        let forge = Gitea::new(
+            &format!("{}/api/v1", server.uri()),
+            "owner/repo",
+            Some("secret".to_string()),
+        )
+        .unwrap();
+        assert_eq!(forge.find_change_request("abc123").await.unwrap(), Some(5));
+        // A PR that was closed without merging didn't bring the commit in
+        assert_eq!(forge.find_change_request("def456").await.unwrap(), None);
+        // Unknown commits give a 404
+        assert_eq!(forge.find_change_request("fff000").await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_pull_request_details() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/repos/owner/repo/pulls/5"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(pull_request_json(5, true)))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/repos/owner/repo/pulls/6"))
+            .respond_with(ResponseTemplate::new(401))
+            .mount(&server)
+            .await;
+
+        let forge = Gitea::new(&format!("{}/api/v1/", server.uri()), "owner/repo", None).unwrap();
+        assert_eq!(
+            forge.change_request(5).await.unwrap(),
+            Some(ChangeRequest {
+                number: 5,
+                title: "PR 5".to_string(),
+                body: Some("Updates `serde` from 1.0.1 to 1.0.2".to_string()),
+                labels: vec!["dependencies".to_string()],
+                author: Some("renovate-bot".to_string()),
+            })
+        );
+        assert_eq!(forge.change_request(7).await.unwrap(), None);
+        assert!(forge.change_request(6).await.is_err());
+    }
+}
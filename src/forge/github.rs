@@ -0,0 +1,774 @@
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use http::header::{ETAG, IF_NONE_MATCH};
+use http::{HeaderMap, StatusCode};
+use octocrab::Octocrab;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+use super::{network_error, parse_response, BulkLookup, ChangeRequest, Forge, Revalidated};
+use crate::ratelimit::{RateLimiter, RetryPolicy};
+
+/// Host of github.com, whose API lives on a separate host that octocrab uses by default
+pub const GITHUB_HOST: &str = "github.com";
+
+/// Commits looked up per GraphQL query, well below the node limit of a single query
+const GRAPHQL_BATCH_SIZE: usize = 50;
+/// Associated pull requests fetched per commit and page; usually there is just one
+const GRAPHQL_PRS_PER_PAGE: usize = 10;
+
+/// Pull requests on GitHub
+pub struct GitHub {
+    client: Octocrab,
+    /// Client for the GraphQL API, which on GitHub Enterprise Server isn't below the REST root
+    graphql: Octocrab,
+    owner: String,
+    repo: String,
+    limiter: RateLimiter,
+}
+
+#[derive(Deserialize)]
+struct SearchResults {
+    items: Vec<SearchItem>,
+}
+
+#[derive(Deserialize)]
+struct SearchItem {
+    number: u64,
+    /// Only set for pull requests
+    pull_request: Option<Value>,
+}
+
+#[derive(Deserialize)]
+struct PullRequest {
+    number: u64,
+    title: Option<String>,
+    body: Option<String>,
+    #[serde(default)]
+    labels: Vec<Label>,
+    user: Option<User>,
+}
+
+impl From<PullRequest> for ChangeRequest {
+    fn from(pr: PullRequest) -> Self {
+        ChangeRequest {
+            number: pr.number,
+            title: pr.title.unwrap_or_default(),
+            body: pr.body,
+            labels: pr.labels.into_iter().map(|label| label.name).collect(),
+            author: pr.user.map(|user| user.login),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct Label {
+    name: String,
+}
+
+#[derive(Deserialize)]
+struct User {
+    login: String,
+}
+
+/// Percent-encode a query string value
+fn encode_query_value(value: &str) -> String {
+    value
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+impl GitHub {
+    pub fn new(client: Octocrab, owner: &str, repo: &str) -> Self {
+        GitHub {
+            graphql: client.clone(),
+            client,
+            owner: owner.to_string(),
+            repo: repo.to_string(),
+            limiter: RateLimiter::default(),
+        }
+    }
+
+    pub fn with_retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.limiter = RateLimiter::new(policy);
+        self
+    }
+
+    /// Use a separate client for GraphQL queries, see [`GitHub::graphql_base_url`]
+    pub fn with_graphql_client(mut self, client: Octocrab) -> Self {
+        self.graphql = client;
+        self
+    }
+
+    /// Base URL for a client that reaches GraphQL at `<base>/graphql`, given the REST API root
+    ///
+    /// github.com serves both from `https://api.github.com`, GitHub Enterprise Server has
+    /// REST at `/api/v3` but GraphQL at `/api/graphql`.
+    pub fn graphql_base_url(api_url: &str) -> String {
+        let api_url = api_url.trim_end_matches('/');
+        api_url.strip_suffix("/v3").unwrap_or(api_url).to_string()
+    }
+
+    /// Authenticated client for github.com, or for the API at `api_url` (GitHub Enterprise Server)
+    pub fn client(token: &str, api_url: Option<&str>) -> Result<Octocrab> {
+        let mut builder = Octocrab::builder().personal_token(token.to_string());
+        if let Some(url) = api_url {
+            builder = builder
+                .base_uri(url)
+                .with_context(|| format!("Invalid GitHub API URL '{}'", url))?;
+        }
+        Ok(builder.build()?)
+    }
+
+    /// API root of a GitHub Enterprise Server instance, `None` for github.com itself
+    ///
+    /// Remotes may name github.com by an alias, e.g. `ssh.github.com` for SSH over port 443.
+    pub fn api_url_for_host(host: &str) -> Option<String> {
+        let host = host.to_lowercase();
+        let name = ["www.", "ssh.", "api."]
+            .iter()
+            .find_map(|prefix| host.strip_prefix(prefix))
+            .unwrap_or(&host);
+        (name != GITHUB_HOST).then(|| format!("https://{}/api/v3", host))
+    }
+}
+
+impl GitHub {
+    /// GET `route` with extra `headers`, or POST `body` to it, retrying while rate limited
+    ///
+    /// Returns the status, headers and body of the response.
+    async fn send(
+        &self,
+        client: &Octocrab,
+        route: &str,
+        body: Option<&Value>,
+        headers: Option<HeaderMap>,
+    ) -> Result<(StatusCode, HeaderMap, String)> {
+        let mut attempt = 0;
+        loop {
+            self.limiter.wait().await;
+            let response = match body {
+                Some(body) => client._post(route, Some(body)).await,
+                None => client._get_with_headers(route, headers.clone()).await,
+            }
+            .map_err(|e| network_error("GitHub", route, e))?;
+            let status = response.status();
+            let headers = response.headers().clone();
+            let text = client
+                .body_to_string(response)
+                .await
+                .map_err(|e| network_error("GitHub", route, e))?;
+            // The limiter holds off the next attempt for as long as needed
+            if self
+                .limiter
+                .check(status.as_u16(), &headers, &text, attempt)?
+                .is_some()
+            {
+                attempt += 1;
+                continue;
+            }
+            return Ok((status, headers, text));
+        }
+    }
+
+    /// The deserialized response, `None` on 404
+    async fn request<T: DeserializeOwned>(
+        &self,
+        client: &Octocrab,
+        route: &str,
+        body: Option<&Value>,
+    ) -> Result<Option<T>> {
+        let (status, _, text) = self.send(client, route, body, None).await?;
+        parse_response("GitHub", route, status, &text)
+    }
+
+    /// Look up the pull requests of up to [`GRAPHQL_BATCH_SIZE`] commits, adding them to `found`
+    async fn find_batch(
+        &self,
+        batch: &[&String],
+        found: &mut HashMap<String, Option<u64>>,
+    ) -> Result<()> {
+        let mut pending: Vec<(String, Option<String>)> =
+            batch.iter().map(|sha| (sha.to_string(), None)).collect();
+        // First pull request of each commit, for when none of them turns out to be merged
+        let mut first_seen: HashMap<String, u64> = HashMap::new();
+
+        while !pending.is_empty() {
+            let payload = json!({
+                "query": associated_pull_requests_query(&pending),
+                "variables": { "owner": self.owner, "name": self.repo },
+            });
+            let response: Value = self
+                .request(&self.graphql, "/graphql", Some(&payload))
+                .await?
+                .ok_or_else(|| anyhow!("GraphQL API not found"))?;
+            if let Some(errors) = response.get("errors").filter(|e| !e.is_null()) {
+                return Err(anyhow!("GraphQL query failed: {}", errors));
+            }
+            let repository = &response["data"]["repository"];
+
+            let mut next_pages = Vec::new();
+            for (i, (sha, _)) in pending.into_iter().enumerate() {
+                let connection = &repository[format!("c{}", i)]["associatedPullRequests"];
+                let nodes = connection["nodes"].as_array().cloned().unwrap_or_default();
+                let merged = nodes
+                    .iter()
+                    .find(|pr| pr["merged"].as_bool() == Some(true))
+                    .and_then(|pr| pr["number"].as_u64());
+                if let Some(number) = merged {
+                    found.insert(sha, Some(number));
+                    continue;
+                }
+                if let Some(number) = nodes.first().and_then(|pr| pr["number"].as_u64()) {
+                    first_seen.entry(sha.clone()).or_insert(number);
+                }
+
+                let page_info = &connection["pageInfo"];
+                match page_info["endCursor"].as_str() {
+                    Some(cursor) if page_info["hasNextPage"].as_bool() == Some(true) => {
+                        next_pages.push((sha, Some(cursor.to_string())));
+                    }
+                    _ => {
+                        let first = first_seen.get(&sha).copied();
+                        found.insert(sha, first);
+                    }
+                }
+            }
+            pending = next_pages;
+        }
+        Ok(())
+    }
+}
+
+/// Query the associated pull requests of each commit, continuing after its cursor if it has one
+fn associated_pull_requests_query(commits: &[(String, Option<String>)]) -> String {
+    let fields: Vec<String> = commits
+        .iter()
+        .enumerate()
+        .map(|(i, (sha, cursor))| {
+            let after = cursor
+                .as_ref()
+                .map(|c| format!(", after: {}", Value::from(c.as_str())))
+                .unwrap_or_default();
+            format!(
+                "c{}: object(oid: \"{}\") {{ ... on Commit {{ associatedPullRequests(first: {}{}) \
+                 {{ nodes {{ number merged }} pageInfo {{ hasNextPage endCursor }} }} }} }}",
+                i, sha, GRAPHQL_PRS_PER_PAGE, after
+            )
+        })
+        .collect();
+    format!(
+        "query($owner: String!, $name: String!) {{ repository(owner: $owner, name: $name) {{ {} }} }}",
+        fields.join(" ")
+    )
+}
+
+#[async_trait]
+impl Forge for GitHub {
+    async fn find_change_request(&self, sha: &str) -> Result<Option<u64>> {
+        // GitHub's search API can find PRs that contain a specific commit SHA
+        let query = format!("repo:{}/{} sha:{}", self.owner, self.repo, sha);
+        let route = format!("/search/issues?q={}", encode_query_value(&query));
+        let results: Option<SearchResults> = self.request(&self.client, &route, None).await?;
+        // Only PRs count, not issues mentioning the commit (PRs have the pull_request field)
+        Ok(results
+            .and_then(|results| results.items.into_iter().next())
+            .filter(|item| item.pull_request.is_some())
+            .map(|item| item.number))
+    }
+
+    /// Looks up the commits' `associatedPullRequests` through GraphQL, in batches
+    ///
+    /// A merged pull request wins over others containing the same commit, as that is the one
+    /// that brought it in. Commits GitHub doesn't know, e.g. not pushed yet, have none. A failed
+    /// batch ends the lookup, keeping what the batches before it found.
+    async fn find_change_requests(&self, shas: &[String]) -> BulkLookup {
+        let mut found = HashMap::new();
+        // Only object IDs can go into the query, anything else is left to the fallback
+        let shas: Vec<&String> = shas
+            .iter()
+            .filter(|sha| sha.chars().all(|c| c.is_ascii_hexdigit()))
+            .collect();
+
+        for batch in shas.chunks(GRAPHQL_BATCH_SIZE) {
+            if let Err(e) = self.find_batch(batch, &mut found).await {
+                return BulkLookup {
+                    found,
+                    error: Some(e),
+                };
+            }
+        }
+        BulkLookup { found, error: None }
+    }
+
+    async fn change_request(&self, number: u64) -> Result<Option<ChangeRequest>> {
+        let route = format!("/repos/{}/{}/pulls/{}", self.owner, self.repo, number);
+        let pr: Option<PullRequest> = self.request(&self.client, &route, None).await?;
+        Ok(pr.map(ChangeRequest::from))
+    }
+
+    /// Conditional request; a 304 doesn't count against the rate limit
+    async fn change_request_if_modified(
+        &self,
+        number: u64,
+        etag: Option<&str>,
+    ) -> Result<Revalidated> {
+        let route = format!("/repos/{}/{}/pulls/{}", self.owner, self.repo, number);
+        let mut headers = HeaderMap::new();
+        if let Some(etag) = etag.and_then(|etag| etag.parse().ok()) {
+            headers.insert(IF_NONE_MATCH, etag);
+        }
+        let (status, headers, text) = self.send(&self.client, &route, None, Some(headers)).await?;
+        if status == StatusCode::NOT_MODIFIED {
+            return Ok(Revalidated::NotModified);
+        }
+        let pr: Option<PullRequest> = parse_response("GitHub", &route, status, &text)?;
+        Ok(Revalidated::Modified {
+            change_request: pr.map(ChangeRequest::from),
+            etag: headers
+                .get(ETAG)
+                .and_then(|etag| etag.to_str().ok())
+                .map(str::to_string),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{github_issue_json, github_pull_json};
+    use crate::warnings::{ForgeError, ProblemKind};
+    use wiremock::matchers::{header, method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    async fn github(server: &MockServer) -> GitHub {
+        let client = Octocrab::builder()
+            .base_uri(server.uri())
+            .unwrap()
+            .build()
+            .unwrap();
+        GitHub::new(client, "owner", "repo")
+    }
+
+    #[tokio::test]
+    async fn test_find_pull_request_by_sha() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/search/issues"))
+            .and(query_param("q", "repo:owner/repo sha:abc123"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "total_count": 1,
+                "incomplete_results": false,
+                "items": [github_issue_json(42, true)]
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/search/issues"))
+            .and(query_param("q", "repo:owner/repo sha:def456"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "total_count": 1,
+                "incomplete_results": false,
+                "items": [github_issue_json(7, false)]
+            })))
+            .mount(&server)
+            .await;
+
+        let forge = github(&server).await;
+        assert_eq!(forge.find_change_request("abc123").await.unwrap(), Some(42));
+        // An issue mentioning the commit is not a pull request
+        assert_eq!(forge.find_change_request("def456").await.unwrap(), None);
+    }
+
+    /// Answers associatedPullRequests queries: commit `00..0N` is in PR 1000 + N,
+    /// commit `aa..` has an unmerged PR on the first page and the merged one on the
+    /// second, and commit `bb..` isn't in any
+    struct GraphQlResponder;
+
+    impl wiremock::Respond for GraphQlResponder {
+        fn respond(&self, request: &wiremock::Request) -> ResponseTemplate {
+            let payload: Value = serde_json::from_slice(&request.body).unwrap();
+            assert_eq!(payload["variables"]["owner"], "owner");
+            let query = payload["query"].as_str().unwrap();
+            let re = regex::Regex::new(
+                r#"(c\d+): object\(oid: "([0-9a-f]+)"\) \{ \.\.\. on Commit \{ associatedPullRequests\(first: \d+(, after: "[^"]*")?\)"#,
+            )
+            .unwrap();
+
+            let mut repository = serde_json::Map::new();
+            for caps in re.captures_iter(query) {
+                let sha = &caps[2];
+                let after = caps.get(3).is_some();
+                let (nodes, has_next) = if sha.starts_with("aa") {
+                    if after {
+                        (json!([{ "number": 2, "merged": true }]), false)
+                    } else {
+                        (json!([{ "number": 1, "merged": false }]), true)
+                    }
+                } else if sha.starts_with("bb") {
+                    (json!([]), false)
+                } else {
+                    let n = u64::from_str_radix(sha, 16).unwrap();
+                    (json!([{ "number": 1000 + n, "merged": true }]), false)
+                };
+                repository.insert(
+                    caps[1].to_string(),
+                    json!({ "associatedPullRequests": {
+                        "nodes": nodes,
+                        "pageInfo": { "hasNextPage": has_next, "endCursor": "cursor" }
+                    }}),
+                );
+            }
+            ResponseTemplate::new(200)
+                .set_body_json(json!({ "data": { "repository": repository } }))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_find_pull_requests_in_batches() {
+        let server = MockServer::start().await;
+        // 60 commits take two queries
+        Mock::given(method("POST"))
+            .and(path("/graphql"))
+            .respond_with(GraphQlResponder)
+            .expect(2)
+            .mount(&server)
+            .await;
+
+        let shas: Vec<String> = (1..=60).map(|n| format!("{:040x}", n)).collect();
+        let found = github(&server)
+            .await
+            .find_change_requests(&shas)
+            .await
+            .found;
+        assert_eq!(found.len(), 60);
+        assert_eq!(found[&shas[0]], Some(1001));
+        assert_eq!(found[&shas[59]], Some(1060));
+    }
+
+    #[tokio::test]
+    async fn test_find_pull_requests_paginated() {
+        let server = MockServer::start().await;
+        // The first query covers both commits, the second only the one with more pages
+        Mock::given(method("POST"))
+            .and(path("/graphql"))
+            .respond_with(GraphQlResponder)
+            .expect(2)
+            .mount(&server)
+            .await;
+
+        let merged_later = "a".repeat(40);
+        let no_pr = "b".repeat(40);
+        let shas = vec![merged_later.clone(), no_pr.clone(), "not-a-sha".to_string()];
+        let found = github(&server)
+            .await
+            .find_change_requests(&shas)
+            .await
+            .found;
+        assert_eq!(found[&merged_later], Some(2));
+        assert_eq!(found[&no_pr], None);
+        // Left to the per-commit fallback
+        assert!(!found.contains_key("not-a-sha"));
+    }
+
+    #[tokio::test]
+    async fn test_graphql_errors_are_reported() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/graphql"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "data": null,
+                "errors": [{ "message": "Could not resolve to a Repository" }]
+            })))
+            .mount(&server)
+            .await;
+
+        let err = github(&server)
+            .await
+            .find_change_requests(&["a".repeat(40)])
+            .await
+            .error
+            .unwrap();
+        assert!(err.to_string().contains("Could not resolve"));
+    }
+
+    #[tokio::test]
+    async fn test_failed_batch_keeps_earlier_batches() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/graphql"))
+            .respond_with(GraphQlResponder)
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/graphql"))
+            .respond_with(ResponseTemplate::new(502))
+            .mount(&server)
+            .await;
+
+        let shas: Vec<String> = (1..=60).map(|n| format!("{:040x}", n)).collect();
+        let lookup = github(&server).await.find_change_requests(&shas).await;
+        assert!(lookup.error.is_some());
+        assert_eq!(lookup.found.len(), GRAPHQL_BATCH_SIZE);
+        assert_eq!(lookup.found[&shas[0]], Some(1001));
+        assert!(!lookup.found.contains_key(&shas[59]));
+    }
+
+    #[tokio::test]
+    async fn test_graphql_rate_limit_is_retried() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/graphql"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("retry-after", "0")
+                    .set_body_json(json!({
+                        "data": null,
+                        "errors": [{
+                            "type": "RATE_LIMITED",
+                            "message": "API rate limit exceeded for user ID 1."
+                        }]
+                    })),
+            )
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/graphql"))
+            .respond_with(GraphQlResponder)
+            .mount(&server)
+            .await;
+
+        let shas = vec![format!("{:040x}", 7)];
+        let lookup = github(&server).await.find_change_requests(&shas).await;
+        assert!(lookup.error.is_none());
+        assert_eq!(lookup.found[&shas[0]], Some(1007));
+    }
+
+    #[tokio::test]
+    async fn test_retries_after_secondary_rate_limit() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/repos/owner/repo/pulls/42"))
+            .respond_with(
+                ResponseTemplate::new(403)
+                    .insert_header("retry-after", "0")
+                    .set_body_json(json!({
+                        "message": "You have exceeded a secondary rate limit",
+                        "documentation_url": "https://docs.github.com/rest"
+                    })),
+            )
+            .up_to_n_times(2)
+            .expect(2)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/repos/owner/repo/pulls/42"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(github_pull_json(
+                42,
+                "Add export",
+                "",
+                &[],
+                "octocat",
+            )))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let forge = github(&server).await;
+        assert_eq!(forge.change_request(42).await.unwrap().unwrap().number, 42);
+    }
+
+    #[tokio::test]
+    async fn test_gives_up_when_rate_limited_too_long() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/search/issues"))
+            .respond_with(
+                ResponseTemplate::new(429)
+                    .insert_header("retry-after", "3600")
+                    .set_body_json(json!({ "message": "API rate limit exceeded" })),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let forge = github(&server).await.with_retry_policy(RetryPolicy {
+            max_wait: std::time::Duration::from_secs(60),
+            ..RetryPolicy::default()
+        });
+        let err = forge.find_change_request("abc123").await.unwrap_err();
+        assert!(err.to_string().contains("Rate limited"));
+    }
+
+    #[test]
+    fn test_encode_query_value() {
+        assert_eq!(
+            encode_query_value("repo:owner/my.repo sha:abc"),
+            "repo%3Aowner%2Fmy.repo%20sha%3Aabc"
+        );
+    }
+
+    #[test]
+    fn test_graphql_base_url() {
+        assert_eq!(
+            GitHub::graphql_base_url("https://github.example.com/api/v3/"),
+            "https://github.example.com/api"
+        );
+        assert_eq!(
+            GitHub::graphql_base_url("https://api.github.com"),
+            "https://api.github.com"
+        );
+    }
+
+    #[test]
+    fn test_api_url_for_host() {
+        assert_eq!(GitHub::api_url_for_host("github.com"), None);
+        assert_eq!(GitHub::api_url_for_host("ssh.github.com"), None);
+        assert_eq!(GitHub::api_url_for_host("www.github.com"), None);
+        assert_eq!(GitHub::api_url_for_host("GitHub.com"), None);
+        assert_eq!(
+            GitHub::api_url_for_host("github.example.com").as_deref(),
+            Some("https://github.example.com/api/v3")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_enterprise_server_api_url() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/v3/search/issues"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "total_count": 1,
+                "incomplete_results": false,
+                "items": [github_issue_json(42, true)]
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api/v3/repos/owner/repo/pulls/42"))
+            .and(header("Authorization", "Bearer secret"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(github_pull_json(
+                42,
+                "Add export",
+                "",
+                &[],
+                "octocat",
+            )))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let api_url = format!("{}/api/v3", server.uri());
+        let client = GitHub::client("secret", Some(&api_url)).unwrap();
+        let forge = GitHub::new(client, "owner", "repo");
+        assert_eq!(forge.find_change_request("abc123").await.unwrap(), Some(42));
+        assert_eq!(forge.change_request(42).await.unwrap().unwrap().number, 42);
+    }
+
+    #[tokio::test]
+    async fn test_pull_request_details() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/repos/owner/repo/pulls/42"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(github_pull_json(
+                42,
+                "Add export",
+                "Body text",
+                &["enhancement"],
+                "octocat",
+            )))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/repos/owner/repo/pulls/43"))
+            .respond_with(ResponseTemplate::new(404).set_body_json(json!({
+                "message": "Not Found",
+                "documentation_url": "https://docs.github.com/rest"
+            })))
+            .mount(&server)
+            .await;
+
+        let forge = github(&server).await;
+        assert_eq!(
+            forge.change_request(42).await.unwrap(),
+            Some(ChangeRequest {
+                number: 42,
+                title: "Add export".to_string(),
+                body: Some("Body text".to_string()),
+                labels: vec!["enhancement".to_string()],
+                author: Some("octocat".to_string()),
+            })
+        );
+        assert_eq!(forge.change_request(43).await.unwrap(), None);
+
+        // An expired token is told apart from other errors, so it can be reported as such
+        Mock::given(method("GET"))
+            .and(path("/repos/owner/repo/pulls/44"))
+            .respond_with(ResponseTemplate::new(401).set_body_json(json!({
+                "message": "Bad credentials",
+                "documentation_url": "https://docs.github.com/rest"
+            })))
+            .mount(&server)
+            .await;
+        let err = forge.change_request(44).await.unwrap_err();
+        assert_eq!(ForgeError::kind_of(&err), ProblemKind::InvalidToken);
+        assert_eq!(
+            err.to_string(),
+            "GitHub request failed: /repos/owner/repo/pulls/44 (HTTP 401): Bad credentials"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_pull_request_revalidation() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/repos/owner/repo/pulls/42"))
+            .and(header("if-none-match", "\"v1\""))
+            .respond_with(ResponseTemplate::new(304))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/repos/owner/repo/pulls/42"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("etag", "\"v2\"")
+                    .set_body_json(github_pull_json(42, "Add export", "", &[], "octocat")),
+            )
+            .mount(&server)
+            .await;
+
+        let forge = github(&server).await;
+        assert_eq!(
+            forge
+                .change_request_if_modified(42, Some("\"v1\""))
+                .await
+                .unwrap(),
+            Revalidated::NotModified
+        );
+        match forge
+            .change_request_if_modified(42, Some("\"v0\""))
+            .await
+            .unwrap()
+        {
+            Revalidated::Modified {
+                change_request,
+                etag,
+            } => {
+                assert_eq!(change_request.unwrap().title, "Add export");
+                assert_eq!(etag.as_deref(), Some("\"v2\""));
+            }
+            other => panic!("expected the pull request, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,242 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use reqwest::header::{HeaderMap, ETAG, IF_NONE_MATCH};
+use reqwest::StatusCode;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+
+use super::{get_with_retries, parse_response, ChangeRequest, Forge, Revalidated};
+use crate::ratelimit::RateLimiter;
+
+/// Merge requests on gitlab.com or a self-hosted GitLab
+pub struct GitLab {
+    client: reqwest::Client,
+    /// API root, e.g. `https://gitlab.com/api/v4`
+    api_url: String,
+    /// Project path, e.g. `group/project`
+    project: String,
+    token: Option<String>,
+    limiter: RateLimiter,
+}
+
+#[derive(Deserialize)]
+struct MergeRequest {
+    iid: u64,
+    title: String,
+    description: Option<String>,
+    #[serde(default)]
+    labels: Vec<String>,
+    state: String,
+    author: Option<User>,
+}
+
+impl From<MergeRequest> for ChangeRequest {
+    fn from(mr: MergeRequest) -> Self {
+        ChangeRequest {
+            number: mr.iid,
+            title: mr.title,
+            body: mr.description,
+            labels: mr.labels,
+            author: mr.author.map(|user| user.username),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct User {
+    username: String,
+}
+
+impl GitLab {
+    /// `token` is sent as a private token; public projects can be read without one
+    pub fn new(api_url: &str, project: &str, token: Option<String>) -> Result<Self> {
+        let client = reqwest::Client::builder()
+            .user_agent(concat!("git-releasenotes/", env!("CARGO_PKG_VERSION")))
+            .build()?;
+        Ok(GitLab {
+            client,
+            api_url: api_url.trim_end_matches('/').to_string(),
+            project: project.to_string(),
+            token,
+            limiter: RateLimiter::default(),
+        })
+    }
+
+    /// API root of the GitLab instance hosting `host`
+    pub fn api_url_for_host(host: &str) -> String {
+        format!("https://{}/api/v4", host)
+    }
+
+    fn url(&self, endpoint: &str) -> String {
+        // The project path is used as its ID, with the slashes encoded
+        format!(
+            "{}/projects/{}/{}",
+            self.api_url,
+            self.project.replace('/', "%2F"),
+            endpoint
+        )
+    }
+
+    /// GET `url`, sending `etag` as `If-None-Match`, retrying while rate limited
+    async fn send(&self, url: &str, etag: Option<&str>) -> Result<(StatusCode, HeaderMap, String)> {
+        get_with_retries(&self.client, &self.limiter, "GitLab", url, |mut request| {
+            if let Some(token) = &self.token {
+                request = request.header("PRIVATE-TOKEN", token);
+            }
+            if let Some(etag) = etag {
+                request = request.header(IF_NONE_MATCH, etag);
+            }
+            request
+        })
+        .await
+    }
+
+    /// GET a project endpoint, `None` on 404
+    async fn get<T: DeserializeOwned>(&self, endpoint: &str) -> Result<Option<T>> {
+        let url = self.url(endpoint);
+        let (status, _, text) = self.send(&url, None).await?;
+        parse_response("GitLab", &url, status, &text)
+    }
+}
+
+#[async_trait]
+impl Forge for GitLab {
+    async fn find_change_request(&self, sha: &str) -> Result<Option<u64>> {
+        let requests: Vec<MergeRequest> = self
+            .get(&format!("repository/commits/{}/merge_requests", sha))
+            .await?
+            .unwrap_or_default();
+        // A commit can be part of several MRs; the merged one is what brought it in. Closed or
+        // open ones didn't, and their labels and title don't belong to the commit.
+        Ok(requests
+            .iter()
+            .find(|mr| mr.state == "merged")
+            .map(|mr| mr.iid))
+    }
+
+    async fn change_request(&self, number: u64) -> Result<Option<ChangeRequest>> {
+        let mr: Option<MergeRequest> = self.get(&format!("merge_requests/{}", number)).await?;
+        Ok(mr.map(ChangeRequest::from))
+    }
+
+    async fn change_request_if_modified(
+        &self,
+        number: u64,
+        etag: Option<&str>,
+    ) -> Result<Revalidated> {
+        let url = self.url(&format!("merge_requests/{}", number));
+        let (status, headers, text) = self.send(&url, etag).await?;
+        if status == StatusCode::NOT_MODIFIED {
+            return Ok(Revalidated::NotModified);
+        }
+        let mr: Option<MergeRequest> = parse_response("GitLab", &url, status, &text)?;
+        Ok(Revalidated::Modified {
+            change_request: mr.map(ChangeRequest::from),
+            etag: headers
+                .get(ETAG)
+                .and_then(|etag| etag.to_str().ok())
+                .map(str::to_string),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use wiremock::matchers::{header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn merge_request_json(iid: u64, state: &str) -> serde_json::Value {
+        json!({
+            "id": 1000 + iid,
+            "iid": iid,
+            "title": format!("MR {}", iid),
+            "description": "Updates `serde` from 1.0.1 to 1.0.2",
+            "labels": ["dependencies"],
+            "state": state,
+            "author": { "id": 1, "username": "renovate-bot", "name": "Renovate Bot" }
+        })
+    }
+
+    #[tokio::test]
+    async fn test_find_merge_request_by_sha() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path(
+                "/api/v4/projects/group%2Fproject/repository/commits/abc123/merge_requests",
+            ))
+            .and(header("PRIVATE-TOKEN", "secret"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!([
+                merge_request_json(3, "closed"),
+                merge_request_json(5, "merged")
+            ])))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path(
+                "/api/v4/projects/group%2Fproject/repository/commits/def456/merge_requests",
+            ))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!([
+                merge_request_json(3, "closed"),
+                merge_request_json(4, "opened")
+            ])))
+            .mount(&server)
+            .await;
+
+        let forge = GitLab::new(
+            &format!("{}/api/v4", server.uri()),
+            "group/project",
+            Some("secret".to_string()),
+        )
+        .unwrap();
+        assert_eq!(forge.find_change_request("abc123").await.unwrap(), Some(5));
+        // Only MRs that weren't merged didn't bring the commit in
+        assert_eq!(forge.find_change_request("def456").await.unwrap(), None);
+        // Unknown commits give a 404
+        assert_eq!(forge.find_change_request("fff000").await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_merge_request_details() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/v4/projects/group%2Fproject/merge_requests/5"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(merge_request_json(5, "merged")))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api/v4/projects/group%2Fproject/merge_requests/6"))
+            .respond_with(ResponseTemplate::new(500))
+            .mount(&server)
+            .await;
+
+        let forge =
+            GitLab::new(&format!("{}/api/v4/", server.uri()), "group/project", None).unwrap();
+        assert_eq!(
+            forge.change_request(5).await.unwrap(),
+            Some(ChangeRequest {
+                number: 5,
+                title: "MR 5".to_string(),
+                body: Some("Updates `serde` from 1.0.1 to 1.0.2".to_string()),
+                labels: vec!["dependencies".to_string()],
+                author: Some("renovate-bot".to_string()),
+            })
+        );
+        assert_eq!(forge.change_request(7).await.unwrap(), None);
+        // Rate limited requests are retried
+        Mock::given(method("GET"))
+            .and(path("/api/v4/projects/group%2Fproject/merge_requests/8"))
+            .respond_with(ResponseTemplate::new(429).insert_header("retry-after", "0"))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api/v4/projects/group%2Fproject/merge_requests/8"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(merge_request_json(8, "merged")))
+            .mount(&server)
+            .await;
+        assert_eq!(forge.change_request(8).await.unwrap().unwrap().number, 8);
+        assert!(forge.change_request(6).await.is_err());
+    }
+}
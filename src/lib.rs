@@ -1,27 +1,57 @@
 // use anyhow::{anyhow, Result};
-use octocrab::Octocrab;
+use config::CommitFacts;
+use ecosystem::Source;
 use regex::Regex;
-use std::collections::HashMap;
-
-#[derive(Debug, PartialEq)]
-pub enum ProcessedCommit {
-    Dependabot(Vec<String>),
-    Other(String),
-}
+use release_note::ReleaseNote;
+use std::collections::{BTreeMap, HashMap};
+use version::Version;
+use warnings::ProblemKind;
+
+pub mod cache;
+pub mod changelog;
+pub mod config;
+pub mod contributors;
+pub mod conventional;
+pub mod ecosystem;
+pub mod forge;
+pub mod lockfile;
+pub mod model;
+pub mod publish;
+pub mod range;
+pub mod ratelimit;
+pub mod release_note;
+pub mod release_yml;
+pub mod remote;
+#[cfg(test)]
+pub(crate) mod test_support;
+pub mod version;
+pub mod warnings;
+
+pub use config::Config;
+pub use forge::Forge;
+pub use model::{
+    Change, DependencyUpdate, ProcessedCommit, RangeInfo, ReleaseNotes, CATEGORY_DEPENDENCIES,
+    CATEGORY_OTHER,
+};
+pub use warnings::Warnings;
 
-#[allow(clippy::too_many_arguments)]
 pub async fn process_commit(
     subject: &str,
     body: &str,
     hash: &str,
     author: &str,
-    include_pr: bool,
-    octocrab: &Option<Octocrab>,
-    owner: &str,
-    repo: &str,
+    forge: Option<&dyn Forge>,
 ) -> Option<ProcessedCommit> {
     process_commit_with_pr(
-        subject, body, hash, author, include_pr, None, octocrab, owner, repo,
+        subject,
+        body,
+        hash,
+        author,
+        None,
+        &Config::default(),
+        &[],
+        forge,
+        &Warnings::default(),
     )
     .await
 }
@@ -32,28 +62,39 @@ pub async fn process_commit_with_pr(
     body: &str,
     hash: &str,
     author: &str,
-    include_pr: bool,
     pr_from_merge: Option<u64>,
-    octocrab: &Option<Octocrab>,
-    owner: &str,
-    repo: &str,
+    config: &Config,
+    paths: &[String],
+    forge: Option<&dyn Forge>,
+    warnings: &Warnings,
 ) -> Option<ProcessedCommit> {
-    let is_dependabot = author.to_lowercase().contains("dependabot");
+    let is_dependabot = config.is_bot(author);
 
-    if subject
-        .to_lowercase()
-        .contains("setting new snapshot version")
-    {
+    let commit_author = CommitFacts {
+        author,
+        ..Default::default()
+    };
+    if config.is_skipped(subject) || config.is_excluded(&commit_author) {
+        return None;
+    }
+    // A release-note trailer in the commit wins over anything its PR says
+    let commit_note = ReleaseNote::from_trailers(body);
+    if commit_note == Some(ReleaseNote::Skip) {
         return None;
     }
 
-    // Extract PR number first so we can add it to output lines when include_pr is true
+    // Extract PR number first so it can be attached to the change and its updates
     // Start with PR number from merge commit if provided
     let mut pr_number: Option<u64> = pr_from_merge;
 
-    let re_merge = Regex::new(r"Merge pull request #([0-9]+)").unwrap();
+    // Merge commits of GitHub, Gitea ("Merge pull request 'title' (#12)") and Bitbucket
+    // ("Merged in branch (pull request #12)")
+    let re_merge =
+        Regex::new(r"(?:Merge pull request (?:'.*' \()?#|\(pull request #)([0-9]+)").unwrap();
     let re_bump = Regex::new(r"Bump the.*\(#([0-9]+)\) \(").unwrap();
     let re_fallback = Regex::new(r"\(#([0-9]+)\)").unwrap();
+    // GitLab merge commits end with "See merge request group/project!123"
+    let re_gitlab_merge = Regex::new(r"See merge request \S*!([0-9]+)").unwrap();
 
     // Only try to extract from subject if we don't already have a PR number
     if pr_number.is_none() {
@@ -69,472 +110,665 @@ pub async fn process_commit_with_pr(
             if let Some(m) = caps.get(1) {
                 pr_number = m.as_str().parse().ok();
             }
+        } else if let Some(caps) = re_gitlab_merge.captures(body) {
+            if let Some(m) = caps.get(1) {
+                pr_number = m.as_str().parse().ok();
+            }
         }
     }
 
-    // Search for PR by SHA fallback (for all commits, not just dependabot)
-    // This searches GitHub for PRs that contain this commit SHA
-    if pr_number.is_none() && !owner.is_empty() && !repo.is_empty() {
-        if let Some(client) = octocrab {
-            // search issues/prs by commit SHA
-            // GitHub's search API can find PRs that contain a specific commit SHA
-            let query = format!("repo:{}/{} sha:{}", owner, repo, hash);
-            match client
-                .search()
-                .issues_and_pull_requests(&query)
-                .send()
-                .await
-            {
-                Ok(page) => {
-                    if let Some(item) = page.items.first() {
-                        // Only get PRs, not issues (PRs have pull_request field)
-                        if item.pull_request.is_some() {
-                            pr_number = Some(item.number);
-                        }
-                    }
-                }
-                Err(e) => {
-                    // Silently fail - API might be rate-limited or commit might not be indexed
-                    // This is expected for some commits
-                    let _ = e;
-                }
+    // Ask the forge which change request contains the commit (for all commits, not just dependabot)
+    if pr_number.is_none() {
+        if let Some(forge) = forge {
+            // A commit the forge doesn't know (e.g. not pushed yet) is no failure, just no PR
+            match forge.find_change_request(hash).await {
+                Ok(found) => pr_number = found,
+                Err(e) => warnings.lookup_failed(format!("commit {}", short_hash(hash)), &e),
             }
         }
     }
 
+    // PR references are kept in `pr_number`, the subject is stored without them
+    let re_pr_remove = Regex::new(r" \(#[0-9]+\)").unwrap();
+    let mut change = Change {
+        subject: re_pr_remove.replace_all(subject, "").to_string(),
+        author: author.to_string(),
+        hash: hash.to_string(),
+        pr_number,
+        category: CATEGORY_OTHER.to_string(),
+        scope: None,
+        breaking: false,
+        labels: Vec::new(),
+        login: None,
+        updates: Vec::new(),
+    };
+
     // Try to parse updates from the commit body first (no API call needed)
     if is_dependabot {
-        let mut update_lines = Vec::new();
-        for line in body.lines() {
-            let lower = line.to_lowercase();
-            if lower.trim().starts_with("updates `") {
-                let clean_line = line.trim();
-                // Add PR number if include_pr is true and we have one
-                let final_line = if include_pr && pr_number.is_some() {
-                    format!("- {} (#{})", clean_line, pr_number.unwrap())
-                } else {
-                    format!("- {}", clean_line)
-                };
-                update_lines.push(final_line);
-            }
-        }
-        if !update_lines.is_empty() {
-            return Some(ProcessedCommit::Dependabot(update_lines));
+        change.category = CATEGORY_DEPENDENCIES.to_string();
+        change.updates = parse_update_lines(body, pr_number, true);
+        let source = Source::detect(&[subject], &[]);
+        change
+            .updates
+            .iter_mut()
+            .for_each(|u| u.set_source(&source));
+        // With a PR, its labels, author and release note may still drop the updates below
+        if !change.updates.is_empty() && pr_number.is_none() {
+            return Some(ProcessedCommit::Dependabot(change));
         }
     }
 
+    // Login of the PR's author, which exclusions and categories may name instead of the commit author
+    let mut login: Option<String> = None;
+    let mut pr_note: Option<ReleaseNote> = None;
     if let Some(pr_num) = pr_number {
         // Fetch PR body
-        let mut updates_found = false;
-        let mut update_lines_vec = Vec::new();
-
-        if let Some(client) = octocrab {
-            if !owner.is_empty() && !repo.is_empty() {
-                if let Ok(pr) = client.pulls(owner, repo).get(pr_num).await {
-                    if let Some(body) = pr.body {
-                        // Parse body for updates
-                        for body_line in body.lines() {
-                            if body_line.starts_with('|')
-                                || body_line.contains("|---")
-                                || body_line.contains("Bumps the")
-                            {
-                                continue;
-                            }
-                            let lower = body_line.to_lowercase();
-                            if lower.trim_start().starts_with("updates `") {
-                                updates_found = true;
-                                let clean_line = body_line.trim();
-                                // Add PR number if include_pr is true
-                                let final_line = if include_pr {
-                                    format!("- {} (#{})", clean_line, pr_num)
-                                } else {
-                                    format!("- {}", clean_line)
-                                };
-                                update_lines_vec.push(final_line);
-                            }
+        if let Some(forge) = forge {
+            let subject = format!("PR #{}", pr_num);
+            match forge.change_request(pr_num).await {
+                Ok(Some(pr)) => {
+                    if let Some(body) = &pr.body {
+                        // Updates listed in the commit body already come from the same PR
+                        if change.updates.is_empty() {
+                            // Tables of version changes are only read from bots' PRs; people
+                            // write tables with arrows for all kinds of other things
+                            let from_bot = is_dependabot
+                                || pr
+                                    .author
+                                    .as_deref()
+                                    .is_some_and(|login| config.is_bot(login));
+                            change.updates = parse_update_lines(body, pr_number, from_bot);
+                        }
+                        pr_note = ReleaseNote::from_description(body);
+                    }
+                    let source = Source::detect(&[&pr.title, &change.subject], &pr.labels);
+                    change
+                        .updates
+                        .iter_mut()
+                        .for_each(|u| u.set_source(&source));
+                    change.labels = pr.labels;
+                    change.login = pr.author.clone();
+                    login = pr.author;
+                    // The PR says what the change is about better than e.g. its merge commit
+                    if config.pr_titles && !pr.title.trim().is_empty() {
+                        change.subject = re_pr_remove.replace_all(pr.title.trim(), "").to_string();
+                        if let Some(login) = &login {
+                            change.author = login.clone();
                         }
                     }
                 }
+                Ok(None) => warnings.push(
+                    ProblemKind::NotFound,
+                    subject,
+                    format!(
+                        "referenced by commit {}, but doesn't exist",
+                        short_hash(hash)
+                    ),
+                ),
+                Err(e) => warnings.lookup_failed(subject, &e),
             }
         }
 
-        if updates_found {
-            return Some(ProcessedCommit::Dependabot(update_lines_vec));
+        // Labels like `skip-changelog` drop the whole PR, dependency updates included
+        let pr_facts = CommitFacts {
+            login: login.as_deref(),
+            labels: &change.labels,
+            ..commit_author
+        };
+        if config.is_excluded(&pr_facts)
+            || (commit_note.is_none() && pr_note == Some(ReleaseNote::Skip))
+        {
+            return None;
+        }
+
+        if !change.updates.is_empty() {
+            change.category = CATEGORY_DEPENDENCIES.to_string();
+            return Some(ProcessedCommit::Dependabot(change));
         }
     }
 
-    // Fallback or no update lines logic
-    let re_pr_remove = Regex::new(r" \(#[0-9]+\)").unwrap();
+    if is_dependabot {
+        // Single-package updates may only say what they update in the subject, which Renovate's
+        // don't say where from; if it doesn't say either, the subject is listed as it is
+        change.updates = DependencyUpdate::parse(&change.subject)
+            .map(|update| DependencyUpdate {
+                pr_numbers: pr_number.into_iter().collect(),
+                ..update
+            })
+            .into_iter()
+            .collect();
+        let source = Source::detect(&[&change.subject], &change.labels);
+        change
+            .updates
+            .iter_mut()
+            .for_each(|u| u.set_source(&source));
+        return Some(ProcessedCommit::Dependabot(change));
+    }
+
+    // Commits matching a configured category get their own section, anything else stays in "Other changes"
+    let cc = conventional::parse(&change.subject, body);
+    let kind = cc.as_ref().map(|cc| cc.kind.as_str());
+    let facts = CommitFacts {
+        kind,
+        subject: &change.subject,
+        body,
+        author,
+        login: login.as_deref(),
+        paths,
+        labels: &change.labels,
+    };
+    if let Some(category) = config.categorize(&facts) {
+        change.category = category.to_string();
+        // Uncategorized commits keep the full subject so "chore:"/"docs:" context isn't lost
+        if let Some(cc) = &cc {
+            change.subject = cc.description.clone();
+            change.scope = cc.scope.clone();
+        }
+    }
+    change.breaking =
+        cc.is_some_and(|cc| cc.breaking) || config.is_breaking_by_labels(&change.labels);
+
+    // The category still comes from the subject, the note only replaces how the entry reads
+    if let Some(ReleaseNote::Text(text)) = commit_note.or(pr_note) {
+        change.subject = text;
+    }
+
+    Some(ProcessedCommit::Other(change))
+}
 
-    let cleaned_subject = if !include_pr {
-        re_pr_remove.replace_all(subject, "").to_string()
-    } else {
-        // If include_pr is true, preserve PR numbers that are already in the subject
-        // and add PR numbers we found that aren't in the subject
-        let mut result = subject.to_string();
-        if let Some(pr_num) = pr_number {
-            // Check if PR number already appears in the subject (in any format)
-            let pr_in_subject = result.contains(&format!("#{}", pr_num));
-            if !pr_in_subject {
-                // Add PR number to the end of the subject in (#123) format
-                result = format!("{} (#{})", result.trim(), pr_num);
+/// Merge the commits of each PR into the entry of the first of them
+///
+/// Meant for notes listing PR titles, where all commits of a PR read the same anyway. The
+/// merged entry is breaking if any of the commits is, and keeps all their dependency updates.
+pub fn collapse_pull_requests(commits: Vec<ProcessedCommit>) -> Vec<ProcessedCommit> {
+    let mut collapsed: Vec<ProcessedCommit> = Vec::new();
+    let mut index_of_pr: HashMap<u64, usize> = HashMap::new();
+
+    for commit in commits {
+        let Some(pr_number) = commit.change().pr_number else {
+            collapsed.push(commit);
+            continue;
+        };
+        match index_of_pr.get(&pr_number) {
+            Some(&index) => {
+                let change = commit.into_change();
+                let existing = collapsed[index].change_mut();
+                existing.breaking |= change.breaking;
+                for update in change.updates {
+                    if !existing.updates.contains(&update) {
+                        existing.updates.push(update);
+                    }
+                }
+            }
+            None => {
+                index_of_pr.insert(pr_number, collapsed.len());
+                collapsed.push(commit);
             }
         }
-        // If include_pr is true, always preserve the subject as-is (it may already contain PR numbers)
-        // This ensures PR numbers in subjects like "(#2887)" are preserved even if extraction failed
-        result
-    };
+    }
+    collapsed
+}
 
-    if is_dependabot {
-        // If it's dependabot but we couldn't find details, just list the subject
-        Some(ProcessedCommit::Dependabot(vec![format!(
-            "- {}",
-            cleaned_subject
-        )]))
-    } else {
-        // Format: - Subject (Author)
-        Some(ProcessedCommit::Other(format!(
-            "- {} ({})",
-            cleaned_subject, author
-        )))
+/// Abbreviated commit hash for messages
+fn short_hash(hash: &str) -> &str {
+    hash.get(..7).unwrap_or(hash)
+}
+
+/// Parse dependabot "Updates `x` from a to b" and "Bumps [x](url) from a to b" lines and, with
+/// `tables`, rows of Renovate's update table from a commit or PR body
+///
+/// Dependabot's tables of grouped updates repeat its "Updates" lines and are left out, they
+/// have no arrow in any column.
+fn parse_update_lines(body: &str, pr_number: Option<u64>, tables: bool) -> Vec<DependencyUpdate> {
+    let mut updates = Vec::new();
+    for line in body.lines() {
+        if line.contains("|---") || line.contains("Bumps the") {
+            continue;
+        }
+        let lower = line.to_lowercase();
+        let lower = lower.trim_start();
+        if lower.starts_with("updates `")
+            || lower.starts_with("bumps [")
+            || (tables && lower.starts_with('|'))
+        {
+            if let Some(mut update) = DependencyUpdate::parse(line.trim()) {
+                update.pr_numbers = pr_number.into_iter().collect();
+                updates.push(update);
+            }
+        }
     }
+    updates
 }
 
-pub fn consolidate_dependabot_updates(updates: Vec<String>) -> Vec<String> {
-    let re_update =
-        Regex::new(r"Updates `([^`]+)` from ([^ ]+) to ([^ ]+)(?: \((#[0-9]+)\))?").unwrap();
-    let re_bump_link =
-        Regex::new(r"Bumps? \[([^\]]+)\]\([^\)]+\) from ([^ ]+) to ([^ ]+)(?: \((#[0-9]+)\))?")
-            .unwrap();
-    let re_bump_simple =
-        Regex::new(r"Bumps? ([^ ]+) from ([^ ]+) to ([^ ]+)(?: \((#[0-9]+)\))?").unwrap();
-    let re_pr_number = Regex::new(r"\(#([0-9]+)\)").unwrap();
+/// One entry per package and direction, in order of package names
+///
+/// Upgrades of a package are merged into one going from the lowest `from` to the highest `to`
+/// version, whatever order the commits came in. Downgrades, e.g. rollbacks, are listed on their
+/// own, as merging them would hide them. Versions that can't be compared, e.g. digests, are only
+/// merged when one update continues where the other stopped.
+pub fn consolidate_updates(updates: Vec<DependencyUpdate>) -> Vec<DependencyUpdate> {
+    // The same package in different manifests, e.g. of a monorepo, is updated separately
+    let mut by_package: BTreeMap<_, Vec<DependencyUpdate>> = BTreeMap::new();
+    for update in updates {
+        let key = (
+            update.package.clone(),
+            update.ecosystem.clone(),
+            update.directory.clone(),
+        );
+        by_package.entry(key).or_default().push(update);
+    }
 
-    let mut package_updates: HashMap<String, (String, String, Vec<u64>)> = HashMap::new();
-    let mut other_updates: Vec<String> = Vec::new();
+    let mut consolidated = Vec::new();
+    for (_, updates) in by_package {
+        let mut merged: Vec<DependencyUpdate> = Vec::new();
+        for update in updates {
+            match merged
+                .iter_mut()
+                .find(|existing| can_merge(existing, &update))
+            {
+                Some(existing) => merge_update(existing, update),
+                None => merged.push(update),
+            }
+        }
+        // Upgrades first, so downgrades stand out below them
+        merged.sort_by_key(|update| update.downgrade);
+        consolidated.extend(merged);
+    }
+    consolidated
+}
 
-    // Iterate through updates
-    // The updates come from process_commit, which processes commits.
-    // If the commits are processed newest to oldest (rev_walk default), then:
-    // Update A: 1.2.4 -> 1.3.0 (Newest)
-    // Update B: 1.2.3 -> 1.2.4 (Older)
-    // We want the result 1.2.3 -> 1.3.0.
-
-    // Logic:
-    // Map: pkg -> (from, to)
-    // When seeing a new update (pkg, new_from, new_to):
-    // Check if we have an existing entry (existing_from, existing_to).
-    // If new_to == existing_from -> We have a chain (new_from -> new_to -> existing_to). Update entry to (new_from, existing_to).
-    // If new_from == existing_to -> We have a chain (existing_from -> existing_to -> new_to). Update entry to (existing_from, new_to).
-    // Else -> Separate chain? For now just overwrite or ignore?
-    // Wait, if we have disjoint updates: 1.0 -> 1.1 and 2.0 -> 2.1.
-    // We probably shouldn't merge them.
-    // But typical dependabot behavior is continuous updates.
-    // If we overwrite, we lose info.
-    // Let's keep a list of updates per package and then merge?
-    // Actually, just trying to merge chains is enough.
-    // If disjoint, we can keep separate entries in a list?
-    // Complex.
-    // Let's stick to the simplest "chaining" logic. If it doesn't chain, treat as new entry.
-    // But since HashMap keys are package names, we can only store one entry per package.
-    // The shell script behavior suggests merging all updates for a package into one "Min -> Max" range.
-    // Let's assume that.
+fn can_merge(existing: &DependencyUpdate, update: &DependencyUpdate) -> bool {
+    if existing.downgrade || update.downgrade {
+        return existing.from == update.from && existing.to == update.to;
+    }
+    match (existing.versions(), update.versions()) {
+        (Some(_), Some(_)) => true,
+        // An update that doesn't say where it started is part of any other going somewhere
+        _ if existing.from.is_empty() || update.from.is_empty() => {
+            let comparable =
+                Version::parse(&existing.to).is_some() && Version::parse(&update.to).is_some();
+            !existing.to.is_empty() && (comparable || existing.to == update.to)
+        }
+        _ => update.to == existing.from || update.from == existing.to,
+    }
+}
 
-    for line in updates {
-        // Extract PR number from line if present
-        let pr_number = re_pr_number
-            .captures(&line)
-            .and_then(|caps| caps.get(1))
-            .and_then(|m| m.as_str().parse::<u64>().ok());
-
-        let parsed = re_update
-            .captures(&line)
-            .map(|caps| {
-                (
-                    caps.get(1).unwrap().as_str().to_string(),
-                    caps.get(2).unwrap().as_str().to_string(),
-                    caps.get(3).unwrap().as_str().to_string(),
-                )
-            })
-            .or_else(|| {
-                re_bump_link.captures(&line).map(|caps| {
-                    (
-                        caps.get(1).unwrap().as_str().to_string(),
-                        caps.get(2).unwrap().as_str().to_string(),
-                        caps.get(3).unwrap().as_str().to_string(),
-                    )
-                })
-            })
-            .or_else(|| {
-                re_bump_simple.captures(&line).map(|caps| {
-                    (
-                        caps.get(1).unwrap().as_str().to_string(),
-                        caps.get(2).unwrap().as_str().to_string(),
-                        caps.get(3).unwrap().as_str().to_string(),
-                    )
-                })
-            });
-
-        if let Some((pkg, from, to)) = parsed {
-            if let Some((existing_from, existing_to, pr_numbers)) = package_updates.get_mut(&pkg) {
-                // Try to chain
-                if &to == existing_from {
-                    *existing_from = from;
-                } else if &from == existing_to {
-                    *existing_to = to;
-                }
-                // Add PR number if present
-                if let Some(pr) = pr_number {
-                    if !pr_numbers.contains(&pr) {
-                        pr_numbers.push(pr);
-                    }
-                }
-            } else {
-                let mut pr_nums = Vec::new();
-                if let Some(pr) = pr_number {
-                    pr_nums.push(pr);
-                }
-                package_updates.insert(pkg, (from, to, pr_nums));
+fn merge_update(existing: &mut DependencyUpdate, update: DependencyUpdate) {
+    match (existing.versions(), update.versions()) {
+        (Some((from, to)), Some((update_from, update_to))) => {
+            if update_from < from {
+                existing.from = update.from;
+            }
+            if update_to > to {
+                existing.to = update.to;
+            }
+        }
+        _ if existing.from.is_empty() || update.from.is_empty() => {
+            if existing.from.is_empty() {
+                existing.from = update.from;
+            }
+            let higher = Version::parse(&existing.to) < Version::parse(&update.to);
+            if higher {
+                existing.to = update.to;
             }
-        } else {
-            other_updates.push(line);
         }
+        _ if update.to == existing.from => existing.from = update.from,
+        _ if update.from == existing.to => existing.to = update.to,
+        _ => {}
     }
+    existing.classify();
+    // Made by a bot if either one was
+    existing.lockfile &= update.lockfile;
+    for pr in update.pr_numbers {
+        if !existing.pr_numbers.contains(&pr) {
+            existing.pr_numbers.push(pr);
+        }
+    }
+}
 
-    let mut final_lines = Vec::new();
-    for (pkg, (from, to, pr_numbers)) in package_updates {
-        let pr_suffix = if !pr_numbers.is_empty() {
-            // Sort PR numbers in descending order (highest first)
-            let mut sorted_prs = pr_numbers.clone();
-            sorted_prs.sort();
-            sorted_prs.reverse();
-            let pr_list: Vec<String> = sorted_prs.iter().map(|n| format!("#{}", n)).collect();
-            format!("  ({})", pr_list.join(", "))
-        } else {
-            String::new()
-        };
-        final_lines.push(format!(
-            "- Updates `{}` from {} to {}{}",
-            pkg, from, to, pr_suffix
-        ));
+/// Line-based wrapper around [`consolidate_updates`]; lines that can't be parsed are passed through
+pub fn consolidate_dependabot_updates(updates: Vec<String>) -> Vec<String> {
+    let mut parsed = Vec::new();
+    let mut other_updates: Vec<String> = Vec::new();
+
+    for line in updates {
+        match DependencyUpdate::parse(&line) {
+            Some(update) => parsed.push(update),
+            None => other_updates.push(line),
+        }
     }
+
+    let mut final_lines: Vec<String> = consolidate_updates(parsed)
+        .iter()
+        .map(|update| update.line(Some('#')))
+        .collect();
     final_lines.extend(other_updates);
 
     final_lines
 }
 
+/// Collect processed commits into structured release notes, consolidating dependency updates
+pub fn build_release_notes(range: RangeInfo, commits: Vec<ProcessedCommit>) -> ReleaseNotes {
+    let changes: Vec<Change> = commits.into_iter().map(|c| c.into_change()).collect();
+    let updates = changes
+        .iter()
+        .flat_map(|change| change.updates.iter().cloned())
+        .collect();
+
+    ReleaseNotes {
+        range,
+        dependency_updates: consolidate_updates(updates),
+        changes,
+        contributors: Vec::new(),
+    }
+}
+
+/// Merge dependency changes found in lockfiles into the notes
+///
+/// A bot's update whose ecosystem or directory isn't known is taken to be in the lockfile with
+/// the same package, if there is only one, so both end up in the same entry. Lockfile changes
+/// no bot made keep `lockfile` set.
+pub fn add_lockfile_updates(notes: &mut ReleaseNotes, lockfile_updates: Vec<DependencyUpdate>) {
+    let mut updates = std::mem::take(&mut notes.dependency_updates);
+    for update in &mut updates {
+        let mut sources: Vec<Source> = lockfile_updates
+            .iter()
+            .filter(|l| l.package == update.package)
+            .filter(|l| update.ecosystem.is_none() || update.ecosystem == l.ecosystem)
+            .filter(|l| update.directory.is_none() || update.directory == l.directory)
+            .map(|l| l.source())
+            .collect();
+        sources.dedup();
+        if let [source] = sources.as_slice() {
+            update.set_source(source);
+        }
+    }
+    updates.extend(lockfile_updates);
+    notes.dependency_updates = consolidate_updates(updates);
+}
+
+/// Markdown notes; `pr_sigil` is put before PR numbers, see [`Change::title`], `None` leaves them out
 pub fn generate_release_notes(
-    mut dependabot_updates: Vec<String>,
-    mut other_changes: Vec<String>,
+    notes: &ReleaseNotes,
+    pr_sigil: Option<char>,
+    config: &Config,
 ) -> String {
-    let mut final_output_lines = Vec::new();
+    let mut sections: Vec<Vec<String>> = Vec::new();
+    let change_line = |c: &Change| format!("- {} ({})", c.title(pr_sigil), c.author);
+
+    // Breaking changes go first so they can't be missed, and aren't repeated further down
+    let breaking_changes: Vec<String> = notes
+        .changes
+        .iter()
+        .filter(|c| c.breaking)
+        .map(change_line)
+        .collect();
+    let heading = format!("## {}:", config.headings.breaking);
+    if let Some(section) = section(&heading, breaking_changes) {
+        sections.push(section);
+    }
 
-    if !dependabot_updates.is_empty() {
-        // Consolidate updates
-        dependabot_updates = consolidate_dependabot_updates(dependabot_updates);
+    // Bots' updates and changes found only in lockfiles are listed apart, under their own heading
+    let mut dependency_sections: Vec<Vec<String>> = [
+        (&config.headings.dependencies, false),
+        (&config.headings.lockfiles, true),
+    ]
+    .into_iter()
+    .filter_map(|(heading, lockfile)| {
+        dependency_section(heading, dependency_groups(notes, pr_sigil, lockfile))
+    })
+    .collect();
+
+    if let Some(first_section) = dependency_sections.first_mut() {
+        // Warn about updates at least as big as configured, naming the kind of those bigger
+        let threshold = config.version_warning;
+        let mut warned_changes: Vec<String> = notes
+            .dependency_updates
+            .iter()
+            .filter_map(|update| Some((update, update.kind?)))
+            .filter(|(_, kind)| threshold.applies_to(*kind))
+            .map(|(update, kind)| {
+                let marker = if threshold.kind() != Some(kind) {
+                    format!(" ({})", kind.description())
+                } else {
+                    String::new()
+                };
+                format!(
+                    "{}: {} → {}{}",
+                    update.package, update.from, update.to, marker
+                )
+            })
+            .collect();
+
+        if !warned_changes.is_empty() {
+            warned_changes.sort();
+            let warning = format!(
+                "⚠ WARNING: {} detected: {}",
+                threshold.description(),
+                warned_changes.join(", ")
+            );
+            first_section.splice(0..0, [warning, "".to_string()]);
+        }
+    }
+    sections.extend(dependency_sections);
+
+    let mut headings: Vec<(&str, String)> = config
+        .categories
+        .iter()
+        .map(|rule| (rule.key.as_str(), format!("## {}:", rule.heading)))
+        .collect();
+    headings.push((CATEGORY_OTHER, format!("## {}:", config.headings.other)));
+
+    for (category, heading) in headings {
+        let lines: Vec<String> = notes
+            .changes
+            .iter()
+            .filter(|c| c.category == category && !c.breaking)
+            .map(change_line)
+            .collect();
+        if let Some(section) = section(&heading, lines) {
+            sections.push(section);
+        }
+    }
 
-        // Check for major version changes
-        let re_update = Regex::new(r"Updates `([^`]+)` from ([^ ]+) to ([^ ]+)").unwrap();
-        let mut major_changes = Vec::new();
+    let contributors = notes.contributors.iter().map(|c| c.line()).collect();
+    let heading = format!("## {}:", config.headings.contributors);
+    if let Some(section) = section(&heading, contributors) {
+        sections.push(section);
+    }
 
-        for line in &dependabot_updates {
-            if let Some(caps) = re_update.captures(line) {
-                let pkg = caps.get(1).unwrap().as_str();
-                let from = caps.get(2).unwrap().as_str();
-                let to = caps.get(3).unwrap().as_str();
+    sections
+        .into_iter()
+        .map(|section| section.join("\n"))
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
 
-                // Simple major version check (first component changed)
-                let from_major = from.split('.').next().unwrap_or("0");
-                let to_major = to.split('.').next().unwrap_or("0");
+/// Consolidated dependency updates, plus dependency commits without parsed updates by subject
+pub(crate) fn dependency_lines(notes: &ReleaseNotes, pr_sigil: Option<char>) -> Vec<String> {
+    [false, true]
+        .into_iter()
+        .flat_map(|lockfile| dependency_groups(notes, pr_sigil, lockfile))
+        .flat_map(|(_, lines)| lines)
+        .collect()
+}
 
-                if let (Ok(f), Ok(t)) = (from_major.parse::<u32>(), to_major.parse::<u32>()) {
-                    if t > f {
-                        major_changes.push(format!("{}: {} → {}", pkg, from, to));
-                    }
-                }
-            }
+/// [`dependency_lines`] of the bots or of lockfiles only, by ecosystem and directory
+///
+/// Groups are ordered by title with unknown ecosystems last.
+pub(crate) fn dependency_groups(
+    notes: &ReleaseNotes,
+    pr_sigil: Option<char>,
+    lockfile: bool,
+) -> Vec<(Source, Vec<String>)> {
+    let mut groups: Vec<(Source, Vec<String>)> = Vec::new();
+    let mut add = |source: Source, line: String| match groups.iter_mut().find(|(s, _)| *s == source)
+    {
+        Some((_, lines)) => lines.push(line),
+        None => groups.push((source, vec![line])),
+    };
+    for update in notes
+        .dependency_updates
+        .iter()
+        .filter(|update| update.lockfile == lockfile)
+    {
+        add(update.source(), update.line(pr_sigil));
+    }
+    for change in &notes.changes {
+        if !lockfile && change.category == CATEGORY_DEPENDENCIES && change.updates.is_empty() {
+            let source = Source::detect(&[&change.subject], &change.labels);
+            add(source, format!("- {}", change.title(pr_sigil)));
         }
+    }
+    groups.sort_by_key(|(source, _)| (source.ecosystem.is_none(), source.title().to_lowercase()));
+    groups
+}
 
-        if !major_changes.is_empty() {
-            major_changes.sort();
-            final_output_lines.push(format!(
-                "⚠ WARNING: Major version changes detected: {}",
-                major_changes.join(", ")
-            ));
-            final_output_lines.push("".to_string());
+/// Dependencies section, with sub-sections only when there is more than one ecosystem or
+/// directory to tell apart; `None` when there are no groups
+fn dependency_section(heading: &str, groups: Vec<(Source, Vec<String>)>) -> Option<Vec<String>> {
+    let mut section = vec![format!("## {}:", heading), "".to_string()];
+    match groups.as_slice() {
+        [] => return None,
+        [(_, lines)] => {
+            let mut lines = lines.clone();
+            lines.sort();
+            section.extend(lines);
+        }
+        _ => {
+            for (i, (source, mut lines)) in groups.into_iter().enumerate() {
+                if i > 0 {
+                    section.push("".to_string());
+                }
+                section.push(format!("### {}:", source.title()));
+                lines.sort();
+                section.extend(lines);
+            }
         }
-
-        final_output_lines.push("## Dependencies updated by dependabot:".to_string());
-        final_output_lines.push("".to_string());
-        dependabot_updates.sort();
-        final_output_lines.extend(dependabot_updates);
-        final_output_lines.push("".to_string());
     }
+    Some(section)
+}
 
-    if !other_changes.is_empty() {
-        other_changes.sort();
-        other_changes.dedup();
-        final_output_lines.push("## Other changes:".to_string());
-        final_output_lines.extend(other_changes);
+/// Heading followed by the sorted, de-duplicated lines, or `None` when there are no lines
+fn section(heading: &str, mut lines: Vec<String>) -> Option<Vec<String>> {
+    if lines.is_empty() {
+        return None;
     }
-
-    final_output_lines.join("\n")
+    lines.sort();
+    lines.dedup();
+    let mut section = vec![heading.to_string()];
+    section.extend(lines);
+    Some(section)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::config::VersionWarning;
+    use crate::warnings::ForgeError;
 
     #[tokio::test]
     async fn test_normal_commit_no_pr() {
-        let res = process_commit("Fix bug", "", "sha", "User", false, &None, "", "").await;
+        let res = process_commit("Fix bug", "", "sha", "User", None).await;
         assert_eq!(
-            res,
-            Some(ProcessedCommit::Other("- Fix bug (User)".to_string()))
+            res.map(|c| c.lines(None)),
+            Some(vec!["- Fix bug (User)".to_string()])
         );
     }
 
     #[tokio::test]
     async fn test_snapshot_version_ignored() {
-        let res = process_commit(
-            "Setting new snapshot version 1.0",
-            "",
-            "sha",
-            "User",
-            false,
-            &None,
-            "",
-            "",
-        )
-        .await;
-        assert_eq!(res, None);
+        let res = process_commit("Setting new snapshot version 1.0", "", "sha", "User", None).await;
+        assert_eq!(res.map(|c| c.lines(None)), None);
     }
 
     #[tokio::test]
     async fn test_pr_number_removal_no_include() {
-        let res = process_commit("Fix bug (#123)", "", "sha", "User", false, &None, "", "").await;
+        let res = process_commit("Fix bug (#123)", "", "sha", "User", None).await;
         assert_eq!(
-            res,
-            Some(ProcessedCommit::Other("- Fix bug (User)".to_string()))
+            res.map(|c| c.lines(None)),
+            Some(vec!["- Fix bug (User)".to_string()])
         );
     }
 
     #[tokio::test]
     async fn test_pr_number_keep_include() {
-        let res = process_commit("Fix bug (#123)", "", "sha", "User", true, &None, "", "").await;
+        let res = process_commit("Fix bug (#123)", "", "sha", "User", None).await;
         assert_eq!(
-            res,
-            Some(ProcessedCommit::Other(
-                "- Fix bug (#123) (User)".to_string()
-            ))
+            res.map(|c| c.lines(Some('#'))),
+            Some(vec!["- Fix bug (#123) (User)".to_string()])
         );
     }
 
     #[tokio::test]
     async fn test_dependabot_no_body() {
-        let res = process_commit(
-            "Bump package (#123)",
-            "",
-            "sha",
-            "dependabot[bot]",
-            false,
-            &None,
-            "",
-            "",
-        )
-        .await;
+        let res = process_commit("Bump package (#123)", "", "sha", "dependabot[bot]", None).await;
         assert_eq!(
-            res,
-            Some(ProcessedCommit::Dependabot(vec![
-                "- Bump package".to_string()
-            ]))
+            res.map(|c| c.lines(None)),
+            Some(vec!["- Bump package".to_string()])
         );
     }
 
     #[tokio::test]
     async fn test_dependabot_with_body() {
         let body = "Bumps [package]...\nUpdates `package` from 1.0 to 1.1\n...";
-        let res = process_commit(
-            "Bump package (#123)",
-            body,
-            "sha",
-            "dependabot[bot]",
-            false,
-            &None,
-            "",
-            "",
-        )
-        .await;
+        let res = process_commit("Bump package (#123)", body, "sha", "dependabot[bot]", None).await;
         assert_eq!(
-            res,
-            Some(ProcessedCommit::Dependabot(vec![
-                "- Updates `package` from 1.0 to 1.1".to_string()
-            ]))
+            res.map(|c| c.lines(None)),
+            Some(vec!["- Updates `package` from 1.0 to 1.1".to_string()])
         );
     }
 
     #[tokio::test]
     async fn test_dependabot_with_body_include_pr() {
         let body = "Bumps [package]...\nUpdates `package` from 1.0 to 1.1\n...";
-        let res = process_commit(
-            "Bump package (#123)",
-            body,
-            "sha",
-            "dependabot[bot]",
-            true,
-            &None,
-            "",
-            "",
-        )
-        .await;
+        let res = process_commit("Bump package (#123)", body, "sha", "dependabot[bot]", None).await;
         assert_eq!(
-            res,
-            Some(ProcessedCommit::Dependabot(vec![
+            res.map(|c| c.lines(Some('#'))),
+            Some(vec![
                 "- Updates `package` from 1.0 to 1.1 (#123)".to_string()
-            ]))
+            ])
         );
     }
 
     #[tokio::test]
     async fn test_merge_pull_request_extraction() {
-        let res = process_commit(
-            "Merge pull request #123 from foo",
-            "",
-            "sha",
-            "User",
-            false,
-            &None,
-            "",
-            "",
-        )
-        .await;
+        let res = process_commit("Merge pull request #123 from foo", "", "sha", "User", None).await;
         assert_eq!(
-            res,
-            Some(ProcessedCommit::Other(
-                "- Merge pull request #123 from foo (User)".to_string()
-            ))
+            res.map(|c| c.lines(None)),
+            Some(vec!["- Merge pull request #123 from foo (User)".to_string()])
         );
     }
 
     #[tokio::test]
-    async fn test_merge_pull_request_with_include_pr() {
+    async fn test_gitea_and_bitbucket_merge_commits() {
         let res = process_commit(
-            "Merge pull request #123 from foo",
+            "Merge pull request 'Add login' (#45) from feature/login into main",
             "",
             "sha",
             "User",
-            true,
-            &None,
-            "",
+            None,
+        )
+        .await;
+        assert_eq!(res.unwrap().change().pr_number, Some(45));
+        let res = process_commit(
+            "Merged in feature/login (pull request #46)",
             "",
+            "sha",
+            "User",
+            None,
         )
         .await;
+        assert_eq!(res.unwrap().change().pr_number, Some(46));
+    }
+
+    #[tokio::test]
+    async fn test_merge_pull_request_with_include_pr() {
+        let res = process_commit("Merge pull request #123 from foo", "", "sha", "User", None).await;
         // PR number is already in subject, so should be kept as-is
         assert_eq!(
-            res,
-            Some(ProcessedCommit::Other(
-                "- Merge pull request #123 from foo (User)".to_string()
-            ))
+            res.map(|c| c.lines(Some('#'))),
+            Some(vec!["- Merge pull request #123 from foo (User)".to_string()])
         );
     }
 
@@ -546,13 +780,11 @@ mod tests {
         // we'll test with a subject that doesn't have the PR number format
         // Actually, the function extracts PR from subject, so we can't easily test this without mocking
         // But we can test that when include_pr is true, existing PR numbers are preserved
-        let res = process_commit("Fix important bug", "", "sha", "User", true, &None, "", "").await;
+        let res = process_commit("Fix important bug", "", "sha", "User", None).await;
         // No PR number extracted, so subject should remain as-is
         assert_eq!(
-            res,
-            Some(ProcessedCommit::Other(
-                "- Fix important bug (User)".to_string()
-            ))
+            res.map(|c| c.lines(Some('#'))),
+            Some(vec!["- Fix important bug (User)".to_string()])
         );
     }
 
@@ -564,18 +796,390 @@ mod tests {
             "",
             "sha",
             "dependabot[bot]",
-            true,
-            &None,
-            "",
-            "",
+            None,
         )
         .await;
         // PR number should be preserved in output
         assert_eq!(
-            res,
-            Some(ProcessedCommit::Dependabot(vec![
+            res.map(|c| c.lines(Some('#'))),
+            Some(vec![
                 "- Bump software.amazon.awssdk:bom in the other-deps group (#2887)".to_string()
-            ]))
+            ])
+        );
+    }
+
+    /// Forge that knows a single change request, for commit `sha-in-pr`
+    struct StubForge(forge::ChangeRequest);
+
+    #[async_trait::async_trait]
+    impl Forge for StubForge {
+        async fn find_change_request(&self, sha: &str) -> anyhow::Result<Option<u64>> {
+            Ok((sha == "sha-in-pr").then_some(self.0.number))
+        }
+
+        async fn change_request(
+            &self,
+            number: u64,
+        ) -> anyhow::Result<Option<forge::ChangeRequest>> {
+            Ok((number == self.0.number).then(|| self.0.clone()))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_pr_found_through_forge() {
+        let forge = StubForge(forge::ChangeRequest {
+            number: 77,
+            title: "Bump the npm group".to_string(),
+            body: Some("Updates `left-pad` from 1.0.0 to 1.1.0".to_string()),
+            ..Default::default()
+        });
+
+        let res = process_commit("Bump the npm group", "", "sha-in-pr", "User", Some(&forge)).await;
+        assert_eq!(
+            res.map(|c| c.lines(Some('#'))),
+            Some(vec![
+                "- Updates `left-pad` from 1.0.0 to 1.1.0 (#77)".to_string()
+            ])
+        );
+
+        let res = process_commit("Fix typo", "", "other-sha", "User", Some(&forge)).await;
+        assert_eq!(res.map(|c| c.change().pr_number), Some(None));
+    }
+
+    #[tokio::test]
+    async fn test_pr_title_and_labels() {
+        let forge = StubForge(forge::ChangeRequest {
+            number: 12,
+            title: "fix(auth): Refresh expired sessions (#12)".to_string(),
+            labels: vec!["security".to_string()],
+            author: Some("octocat".to_string()),
+            ..Default::default()
+        });
+        let mut config = Config::parse(
+            r#"
+            [[categories]]
+            key = "security"
+            heading = "Security"
+            labels = ["security"]
+            "#,
+        )
+        .unwrap();
+        let warnings = Warnings::default();
+        let process = |config: Config| {
+            let (forge, warnings) = (&forge, &warnings);
+            async move {
+                process_commit_with_pr(
+                    "Merge pull request #12 from octocat/sessions",
+                    "",
+                    "sha",
+                    "Maintainer",
+                    None,
+                    &config,
+                    &[],
+                    Some(forge),
+                    warnings,
+                )
+                .await
+                .unwrap()
+                .into_change()
+            }
+        };
+
+        // Labels are used for categories either way
+        let change = process(config.clone()).await;
+        assert_eq!(
+            change.subject,
+            "Merge pull request #12 from octocat/sessions"
+        );
+        assert_eq!(change.category, "security");
+        assert_eq!(change.labels, vec!["security"]);
+
+        config.pr_titles = true;
+        let change = process(config.clone()).await;
+        assert_eq!(change.subject, "Refresh expired sessions");
+        assert_eq!(change.scope.as_deref(), Some("auth"));
+        assert_eq!(change.author, "octocat");
+        assert_eq!(change.category, "security");
+    }
+
+    #[tokio::test]
+    async fn test_skip_and_breaking_labels() {
+        let pr = |labels: &[&str]| {
+            StubForge(forge::ChangeRequest {
+                number: 5,
+                title: "Remove the v1 API".to_string(),
+                labels: labels.iter().map(|l| l.to_string()).collect(),
+                ..Default::default()
+            })
+        };
+
+        let forge = pr(&["Skip-Changelog"]);
+        let res = process_commit("Remove the v1 API (#5)", "", "sha", "User", Some(&forge)).await;
+        assert_eq!(res, None);
+
+        let forge = pr(&["breaking"]);
+        let res = process_commit("Remove the v1 API (#5)", "", "sha", "User", Some(&forge)).await;
+        assert!(res.unwrap().change().breaking);
+    }
+
+    #[tokio::test]
+    async fn test_bot_updates_in_commit_body_still_check_the_pr() {
+        let body = "Updates `serde` from 1.0.1 to 1.0.2";
+        let pr = |labels: &[&str], author: &str, body: &str| {
+            StubForge(forge::ChangeRequest {
+                number: 9,
+                title: "Bump serde from 1.0.1 to 1.0.2".to_string(),
+                body: Some(body.to_string()),
+                labels: labels.iter().map(|l| l.to_string()).collect(),
+                author: Some(author.to_string()),
+            })
+        };
+        let process = |forge: StubForge, config: Config| async move {
+            process_commit_with_pr(
+                "Bump serde from 1.0.1 to 1.0.2 (#9)",
+                body,
+                "sha",
+                "dependabot[bot]",
+                None,
+                &config,
+                &[],
+                Some(&forge),
+                &Warnings::default(),
+            )
+            .await
+        };
+
+        let res = process(pr(&[], "dependabot[bot]", body), Config::default()).await;
+        assert_eq!(
+            res.map(|c| c.lines(Some('#'))),
+            Some(vec![
+                "- Updates `serde` from 1.0.1 to 1.0.2 (#9)".to_string()
+            ])
+        );
+        let res = process(
+            pr(&["skip-changelog"], "dependabot[bot]", body),
+            Config::default(),
+        )
+        .await;
+        assert_eq!(res, None);
+        let config = Config {
+            skip_authors: vec!["mergebot".to_string()],
+            ..Config::default()
+        };
+        assert_eq!(process(pr(&[], "mergebot", body), config).await, None);
+        let pr_body = format!("{}\n\n```release-note\nNONE\n```", body);
+        let res = process(pr(&[], "dependabot[bot]", &pr_body), Config::default()).await;
+        assert_eq!(res, None);
+    }
+
+    #[tokio::test]
+    async fn test_tables_in_human_prs_are_no_updates() {
+        let pr = |author: &str| {
+            StubForge(forge::ChangeRequest {
+                number: 5,
+                title: "Raise the request timeout".to_string(),
+                body: Some(
+                    "| Setting | Change |\n|---|---|\n| timeout | `30s` -> `60s` |".to_string(),
+                ),
+                author: Some(author.to_string()),
+                ..Default::default()
+            })
+        };
+
+        let forge = pr("octocat");
+        let res = process_commit(
+            "fix: Raise the request timeout (#5)",
+            "",
+            "sha",
+            "User",
+            Some(&forge),
+        )
+        .await
+        .unwrap();
+        assert!(matches!(res, ProcessedCommit::Other(_)));
+        assert_eq!(res.change().category, "fixes");
+        assert!(res.change().updates.is_empty());
+
+        // A bot's PR merged by a person still lists its table
+        let forge = pr("renovate[bot]");
+        let res = process_commit(
+            "Update dependency timeout (#5)",
+            "",
+            "sha",
+            "User",
+            Some(&forge),
+        )
+        .await
+        .unwrap();
+        assert_eq!(res.change().updates.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_release_note_text() {
+        let pr = |body: &str| {
+            StubForge(forge::ChangeRequest {
+                number: 7,
+                title: "Refactor session store".to_string(),
+                body: Some(body.to_string()),
+                ..Default::default()
+            })
+        };
+
+        let forge = pr("## Release notes\nSessions no longer expire while in use\n");
+        let res = process_commit(
+            "fix(auth): refresh tokens (#7)",
+            "",
+            "sha",
+            "User",
+            Some(&forge),
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            res.change().subject,
+            "Sessions no longer expire while in use"
+        );
+        assert_eq!(res.change().category, "fixes");
+
+        // The commit's trailer wins over the PR, also over its NONE
+        let body = "Details.\n\nRelease-Note: Tokens are refreshed in the background";
+        let forge = pr("```release-note\nNONE\n```");
+        let res = process_commit(
+            "fix: refresh tokens (#7)",
+            body,
+            "sha",
+            "User",
+            Some(&forge),
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            res.change().subject,
+            "Tokens are refreshed in the background"
+        );
+
+        let res = process_commit("fix: refresh tokens (#7)", "", "sha", "User", Some(&forge)).await;
+        assert_eq!(res, None);
+        let res = process_commit("fix: typo", "Changelog: none", "sha", "User", None).await;
+        assert_eq!(res, None);
+    }
+
+    #[test]
+    fn test_collapse_pull_requests() {
+        let commit = |subject: &str, pr_number: Option<u64>, breaking: bool| {
+            ProcessedCommit::Other(Change {
+                pr_number,
+                breaking,
+                ..change(subject, CATEGORY_OTHER)
+            })
+        };
+        let collapsed = collapse_pull_requests(vec![
+            commit("Add export", Some(3), false),
+            commit("Fix typo", None, false),
+            commit("Add export", Some(3), true),
+            commit("Fix typo", None, false),
+            commit("Drop Python 2", Some(2), false),
+        ]);
+        let summary: Vec<(&str, Option<u64>, bool)> = collapsed
+            .iter()
+            .map(|c| {
+                (
+                    c.change().subject.as_str(),
+                    c.change().pr_number,
+                    c.change().breaking,
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("Add export", Some(3), true),
+                ("Fix typo", None, false),
+                ("Fix typo", None, false),
+                ("Drop Python 2", Some(2), false),
+            ]
+        );
+    }
+
+    /// Forge whose token has expired
+    struct ExpiredTokenForge;
+
+    #[async_trait::async_trait]
+    impl Forge for ExpiredTokenForge {
+        async fn find_change_request(&self, _sha: &str) -> anyhow::Result<Option<u64>> {
+            Err(ForgeError::new(ProblemKind::InvalidToken, "Bad credentials").into())
+        }
+
+        async fn change_request(
+            &self,
+            number: u64,
+        ) -> anyhow::Result<Option<forge::ChangeRequest>> {
+            if number == 1 {
+                return Ok(None);
+            }
+            Err(ForgeError::new(ProblemKind::InvalidToken, "Bad credentials").into())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_failed_lookups_are_reported() {
+        let warnings = Warnings::default();
+        let config = Config::default();
+        let process = |subject: &'static str| {
+            process_commit_with_pr(
+                subject,
+                "",
+                "0123456789abcdef",
+                "dependabot[bot]",
+                None,
+                &config,
+                &[],
+                Some(&ExpiredTokenForge),
+                &warnings,
+            )
+        };
+        // The commit is still listed, just without details
+        assert!(process("Bump serde").await.is_some());
+        assert!(process("Bump tokio (#2)").await.is_some());
+        assert!(process("Bump regex (#1)").await.is_some());
+
+        let found: Vec<(ProblemKind, String)> = warnings
+            .to_vec()
+            .into_iter()
+            .map(|w| (w.kind, w.subject))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (ProblemKind::InvalidToken, "commit 0123456".to_string()),
+                (ProblemKind::InvalidToken, "PR #2".to_string()),
+                (ProblemKind::NotFound, "PR #1".to_string()),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_pr_number_from_gitlab_merge_commit() {
+        let res = process_commit(
+            "Merge branch 'fix-login' into 'main'",
+            "Fix login\n\nSee merge request group/project!15",
+            "sha",
+            "User",
+            None,
+        )
+        .await
+        .unwrap();
+        assert_eq!(res.change().pr_number, Some(15));
+        // On GitLab #15 would be issue 15
+        assert_eq!(
+            res.lines(Some('!')),
+            vec!["- Merge branch 'fix-login' into 'main' (!15) (User)"]
+        );
+        let mut update = DependencyUpdate::new("serde", "1.0.1", "1.0.2", Some(15));
+        update.pr_numbers.push(16);
+        assert_eq!(
+            update.line(Some('!')),
+            "- Updates `serde` from 1.0.1 to 1.0.2  (!16, !15)"
         );
     }
 
@@ -587,19 +1191,19 @@ mod tests {
             "",
             "sha",
             "dependabot[bot]",
-            true,
             Some(2880),
-            &None,
-            "",
-            "",
+            &Config::default(),
+            &[],
+            None,
+            &Warnings::default(),
         )
         .await;
         // PR number should be added to output
         assert_eq!(
-            res,
-            Some(ProcessedCommit::Dependabot(vec![
+            res.map(|c| c.lines(Some('#'))),
+            Some(vec![
                 "- Bump the fiks-deps group across 1 directory with 8 updates (#2880)".to_string()
-            ]))
+            ])
         );
     }
 
@@ -653,6 +1257,137 @@ mod tests {
         assert_eq!(res, expected);
     }
 
+    #[test]
+    fn test_consolidate_semver_ranges() {
+        let update = |package, from, to, pr| DependencyUpdate::new(package, from, to, Some(pr));
+        let consolidated = consolidate_updates(vec![
+            // Not chained: a grouped update and a later one skipping a version
+            update("lib", "v1.2.0", "v1.3.0", 1),
+            update("lib", "v1.4.0", "v1.5.0-rc.1", 2),
+            update("lib", "v1.0.9", "v1.2.0", 3),
+            update("lib", "v1.5.0-rc.1", "v1.5.0", 4),
+            update("spring-core", "5.3.1.RELEASE", "5.3.2.RELEASE", 5),
+            update("spring-core", "5.3.2.RELEASE", "5.3.1.RELEASE", 6),
+            update("app", "0.9.0", "0.10.0", 7),
+            update("node", "sha256:aaa", "sha256:bbb", 8),
+            update("node", "sha256:ccc", "sha256:ddd", 9),
+        ]);
+        let lines: Vec<String> = consolidated.iter().map(|u| u.line(Some('#'))).collect();
+        assert_eq!(
+            lines,
+            vec![
+                "- Updates `app` from 0.9.0 to 0.10.0  (#7)",
+                "- Updates `lib` from v1.0.9 to v1.5.0  (#4, #3, #2, #1)",
+                "- Updates `node` from sha256:aaa to sha256:bbb  (#8)",
+                "- Updates `node` from sha256:ccc to sha256:ddd  (#9)",
+                "- Updates `spring-core` from 5.3.1.RELEASE to 5.3.2.RELEASE  (#5)",
+                "- Downgrades `spring-core` from 5.3.2.RELEASE to 5.3.1.RELEASE  (#6)",
+            ]
+        );
+        assert!(consolidated[5].downgrade);
+    }
+
+    #[test]
+    fn test_consolidate_renovate_and_dependabot() {
+        let updates = vec![
+            "- Updates `serde` from 1.0.198 to 1.0.199".to_string(),
+            "| [serde](https://serde.rs) ([source](https://github.com/serde-rs/serde)) | \
+             dependencies | patch | `1.0.199` -> `1.0.200` |"
+                .to_string(),
+            "chore(deps): update rust crate serde to 1.0.201".to_string(),
+            "| actions/checkout | action | major | `v3` → `v4` |".to_string(),
+        ];
+
+        let mut res = consolidate_dependabot_updates(updates);
+        res.sort();
+
+        let expected = vec![
+            "- Updates `actions/checkout` from v3 to v4".to_string(),
+            "- Updates `serde` from 1.0.198 to 1.0.201".to_string(),
+        ];
+        assert_eq!(res, expected);
+    }
+
+    #[tokio::test]
+    async fn test_renovate_subjects() {
+        let renovate = |subject: &'static str| async move {
+            process_commit(subject, "", "sha", "renovate[bot]", None)
+                .await
+                .unwrap()
+        };
+        let commits = vec![
+            renovate("Update dependency foo to v2.3.1 (#4)").await,
+            renovate("chore(deps): update rust crate serde to 1.0.200").await,
+            renovate("Update actions/checkout action to v4").await,
+        ];
+        let lines: Vec<Vec<String>> = commits.iter().map(|c| c.lines(Some('#'))).collect();
+        assert_eq!(
+            lines,
+            vec![
+                vec!["- Updates `foo` to v2.3.1 (#4)"],
+                vec!["- Updates `serde` to 1.0.200"],
+                vec!["- Updates `actions/checkout` to v4"],
+            ]
+        );
+
+        // The lockfile tells where the update started
+        let mut notes = build_release_notes(RangeInfo::default(), commits);
+        add_lockfile_updates(
+            &mut notes,
+            vec![DependencyUpdate {
+                ecosystem: Some("cargo".to_string()),
+                lockfile: true,
+                ..DependencyUpdate::new("serde", "1.0.199", "1.0.200", None)
+            }],
+        );
+        let lines: Vec<String> = notes
+            .dependency_updates
+            .iter()
+            .map(|u| u.line(None))
+            .collect();
+        assert_eq!(
+            lines,
+            vec![
+                "- Updates `actions/checkout` to v4",
+                "- Updates `foo` to v2.3.1",
+                "- Updates `serde` from 1.0.199 to 1.0.200",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_renovate_updates_from_pr_table() {
+        let forge = StubForge(forge::ChangeRequest {
+            number: 9,
+            title: "chore(deps): update rust crate serde to 1.0.200".to_string(),
+            body: Some(
+                "This PR contains the following updates:\n\n\
+                 | Package | Type | Update | Change |\n\
+                 |---|---|---|---|\n\
+                 | [serde](https://serde.rs) | dependencies | patch | `1.0.199` -> `1.0.200` |\n\
+                 | [anyhow](https://github.com/dtolnay/anyhow) | dependencies | patch | \
+                 [`1.0.79` -> `1.0.80`](https://renovatebot.com/diffs/crates/anyhow/1.0.79/1.0.80) |\n"
+                    .to_string(),
+            ),
+            ..Default::default()
+        });
+        let res = process_commit(
+            "chore(deps): update rust crate serde to 1.0.200 (#9)",
+            "",
+            "sha",
+            "renovate[bot]",
+            Some(&forge),
+        )
+        .await;
+        assert_eq!(
+            res.map(|c| c.lines(Some('#'))),
+            Some(vec![
+                "- Updates `serde` from 1.0.199 to 1.0.200 (#9)".to_string(),
+                "- Updates `anyhow` from 1.0.79 to 1.0.80 (#9)".to_string(),
+            ])
+        );
+    }
+
     #[test]
     fn test_consolidate_preserves_pr_numbers() {
         let updates = vec![
@@ -723,16 +1458,48 @@ mod tests {
         assert_eq!(lib_line, "- Updates `lib` from 1.0 to 1.2");
     }
 
+    fn change(subject: &str, category: &str) -> Change {
+        Change {
+            subject: subject.to_string(),
+            author: "User".to_string(),
+            hash: "sha".to_string(),
+            pr_number: None,
+            category: category.to_string(),
+            scope: None,
+            breaking: false,
+            labels: Vec::new(),
+            login: None,
+            updates: Vec::new(),
+        }
+    }
+
+    fn notes(updates: Vec<DependencyUpdate>, other: &[&str]) -> ReleaseNotes {
+        let mut commits: Vec<ProcessedCommit> = updates
+            .into_iter()
+            .map(|update| {
+                let mut c = change("Bump", CATEGORY_DEPENDENCIES);
+                c.updates = vec![update];
+                ProcessedCommit::Dependabot(c)
+            })
+            .collect();
+        commits.extend(
+            other
+                .iter()
+                .map(|s| ProcessedCommit::Other(change(s, CATEGORY_OTHER))),
+        );
+        build_release_notes(RangeInfo::default(), commits)
+    }
+
     #[test]
     fn test_generate_release_notes_empty() {
-        let output = generate_release_notes(vec![], vec![]);
+        let output = generate_release_notes(&notes(vec![], &[]), None, &Config::default());
         assert_eq!(output, "");
     }
 
     #[test]
     fn test_generate_release_notes_dependabot_only() {
-        let updates = vec!["- Updates `lib` from 1.0.0 to 1.1.0".to_string()];
-        let output = generate_release_notes(updates, vec![]);
+        let updates = vec![DependencyUpdate::new("lib", "1.0.0", "1.1.0", None)];
+        let output = generate_release_notes(&notes(updates, &[]), None, &Config::default());
         assert!(output.contains("## Dependencies updated by dependabot:"));
         assert!(output.contains("- Updates `lib` from 1.0.0 to 1.1.0"));
         assert!(!output.contains("## Other changes:"));
@@ -741,32 +1508,345 @@ mod tests {
 
     #[test]
     fn test_generate_release_notes_other_only() {
-        let other = vec!["- Fix something".to_string(), "- Add something".to_string()];
-        let output = generate_release_notes(vec![], other);
+        let output = generate_release_notes(
+            &notes(vec![], &["Fix something", "Add something"]),
+            None,
+            &Config::default(),
+        );
         assert!(!output.contains("## Dependencies updated by dependabot:"));
         assert!(output.contains("## Other changes:"));
         assert!(output.contains("- Fix something"));
         assert!(output.contains("- Add something"));
     }
 
+    #[tokio::test]
+    async fn test_dependency_sub_sections() {
+        let bump = |subject: &str, body: &str| {
+            let subject = subject.to_string();
+            let body = body.to_string();
+            async move {
+                process_commit(&subject, &body, "sha", "dependabot[bot]", None)
+                    .await
+                    .unwrap()
+            }
+        };
+        let commits = vec![
+            bump(
+                "Bump the cargo group in /backend with 1 update",
+                "Updates `serde` from 1.0.1 to 1.0.2",
+            )
+            .await,
+            bump(
+                "Bump the npm_and_yarn group in /frontend with 1 update",
+                "Updates `lodash` from 4.17.20 to 4.17.21",
+            )
+            .await,
+            bump(
+                "Bump mdbook from 0.4.36 to 0.4.37 in /docs",
+                "Updates `mdbook` from 0.4.36 to 0.4.37",
+            )
+            .await,
+            bump("Bump the github-actions group with 2 updates", "").await,
+            bump(
+                "Bump the terraform group with 1 update",
+                "Updates `hashicorp/aws` from 5.0.0 to 5.1.0",
+            )
+            .await,
+        ];
+        let notes = build_release_notes(RangeInfo::default(), commits);
+        let output = generate_release_notes(&notes, None, &Config::default());
+        assert_eq!(
+            output,
+            "## Dependencies updated by dependabot:\n\n\
+             ### Cargo in /backend:\n\
+             - Updates `serde` from 1.0.1 to 1.0.2\n\n\
+             ### GitHub Actions:\n\
+             - Bump the github-actions group with 2 updates\n\n\
+             ### npm in /frontend:\n\
+             - Updates `lodash` from 4.17.20 to 4.17.21\n\n\
+             ### Terraform:\n\
+             - Updates `hashicorp/aws` from 5.0.0 to 5.1.0\n\n\
+             ### Dependencies in /docs:\n\
+             - Updates `mdbook` from 0.4.36 to 0.4.37"
+        );
+    }
+
+    #[test]
+    fn test_add_lockfile_updates() {
+        let mut notes = notes(
+            vec![DependencyUpdate::new("serde", "1.0.1", "1.0.2", Some(5))],
+            &[],
+        );
+        let lockfile_update = |package, from, to| DependencyUpdate {
+            ecosystem: Some("cargo".to_string()),
+            lockfile: true,
+            ..DependencyUpdate::new(package, from, to, None)
+        };
+        add_lockfile_updates(
+            &mut notes,
+            vec![
+                // Upgraded by hand after the bot's PR
+                lockfile_update("serde", "1.0.1", "1.0.3"),
+                lockfile_update("anyhow", "", "1.0.79"),
+            ],
+        );
+        let lines: Vec<String> = notes
+            .dependency_updates
+            .iter()
+            .map(|u| u.line(Some('#')))
+            .collect();
+        assert_eq!(
+            lines,
+            vec![
+                "- Adds `anyhow` 1.0.79",
+                "- Updates `serde` from 1.0.1 to 1.0.3  (#5)"
+            ]
+        );
+        // Only what no bot did is listed as coming from lockfiles
+        assert_eq!(
+            generate_release_notes(&notes, None, &Config::default()),
+            "## Dependencies updated by dependabot:\n\n\
+             - Updates `serde` from 1.0.1 to 1.0.3\n\n\
+             ## Dependencies changed in lockfiles:\n\n\
+             - Adds `anyhow` 1.0.79"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_lockfile_updates_merged_with_single_bumps() {
+        let body = "Bumps [serde](https://github.com/serde-rs/serde) from 1.0.1 to 1.0.2.\n\
+                    - [Release notes](https://github.com/serde-rs/serde/releases)\n\n\
+                    ---\nupdated-dependencies:\n- dependency-name: serde\n";
+        let commits = vec![
+            process_commit(
+                "Bump serde from 1.0.1 to 1.0.2 (#7)",
+                body,
+                "sha1",
+                "dependabot[bot]",
+                None,
+            )
+            .await
+            .unwrap(),
+            // Squash merges may keep nothing but the subject
+            process_commit(
+                "Bump regex from 1.10.2 to 1.10.3 (#8)",
+                "",
+                "sha2",
+                "dependabot[bot]",
+                None,
+            )
+            .await
+            .unwrap(),
+        ];
+        let mut notes = build_release_notes(RangeInfo::default(), commits);
+        let lockfile_update = |package, from, to| DependencyUpdate {
+            ecosystem: Some("cargo".to_string()),
+            lockfile: true,
+            ..DependencyUpdate::new(package, from, to, None)
+        };
+        add_lockfile_updates(
+            &mut notes,
+            vec![
+                lockfile_update("regex", "1.10.2", "1.10.3"),
+                lockfile_update("serde", "1.0.1", "1.0.2"),
+            ],
+        );
+        assert_eq!(
+            generate_release_notes(&notes, Some('#'), &Config::default()),
+            "## Dependencies updated by dependabot:\n\n\
+             - Updates `regex` from 1.10.2 to 1.10.3  (#8)\n\
+             - Updates `serde` from 1.0.1 to 1.0.2  (#7)"
+        );
+    }
+
     #[test]
     fn test_generate_release_notes_major_version_warning() {
-        let updates = vec!["- Updates `lib` from 1.0.0 to 2.0.0".to_string()];
-        let output = generate_release_notes(updates, vec![]);
+        let updates = vec![DependencyUpdate::new("lib", "1.0.0", "2.0.0", None)];
+        let output = generate_release_notes(&notes(updates, &[]), None, &Config::default());
         assert!(output.contains("WARNING: Major version changes detected: lib: 1.0.0 → 2.0.0"));
     }
 
+    #[test]
+    fn test_generate_release_notes_version_warning_threshold() {
+        let updates = || {
+            vec![
+                DependencyUpdate::new("tokio", "0.9.3", "0.10.0", None),
+                DependencyUpdate::new("serde", "1.0.1", "1.1.0", None),
+                DependencyUpdate::new("clap", "4.5.0", "5.0.0-rc.1", None),
+                DependencyUpdate::new("regex", "1.10.2", "1.10.3", None),
+            ]
+        };
+        let warning = |config: &Config| {
+            generate_release_notes(&notes(updates(), &[]), None, config)
+                .lines()
+                .find(|line| line.contains("WARNING"))
+                .map(str::to_string)
+        };
+
+        assert_eq!(
+            warning(&Config::default()).as_deref(),
+            Some(
+                "⚠ WARNING: Major version changes detected: clap: 4.5.0 → 5.0.0-rc.1 (pre-release), \
+                 tokio: 0.9.3 → 0.10.0"
+            )
+        );
+        let config = Config {
+            version_warning: VersionWarning::Minor,
+            ..Config::default()
+        };
+        assert_eq!(
+            warning(&config).as_deref(),
+            Some(
+                "⚠ WARNING: Version changes detected: clap: 4.5.0 → 5.0.0-rc.1 (pre-release), \
+                 serde: 1.0.1 → 1.1.0, tokio: 0.9.3 → 0.10.0 (major)"
+            )
+        );
+        let config = Config {
+            version_warning: VersionWarning::Never,
+            ..Config::default()
+        };
+        assert_eq!(warning(&config), None);
+    }
+
     #[test]
     fn test_generate_release_notes_sorting_and_deduplication() {
-        let other = vec![
-            "- B change".to_string(),
-            "- A change".to_string(),
-            "- A change".to_string(),
-        ];
-        let output = generate_release_notes(vec![], other);
+        let output = generate_release_notes(
+            &notes(vec![], &["B change", "A change", "A change"]),
+            None,
+            &Config::default(),
+        );
         let lines: Vec<&str> = output.lines().collect();
         // Skip header "## Other changes:"
         let content_lines: Vec<&str> = lines.into_iter().filter(|l| l.starts_with("- ")).collect();
-        assert_eq!(content_lines, vec!["- A change", "- B change"]);
+        assert_eq!(
+            content_lines,
+            vec!["- A change (User)", "- B change (User)"]
+        );
+    }
+
+    #[test]
+    fn test_generate_release_notes_contributors() {
+        let mut notes = notes(vec![], &["Fix something"]);
+        notes.contributors = vec![contributors::Contributor {
+            name: "Jane Doe".to_string(),
+            email: "jane@example.com".to_string(),
+            login: Some("jdoe".to_string()),
+            first_time: true,
+        }];
+        let output = generate_release_notes(&notes, None, &Config::default());
+        assert!(output.ends_with("## Contributors:\n- Jane Doe (@jdoe), first contribution"));
+
+        let json = serde_json::to_value(&notes).unwrap();
+        assert_eq!(
+            json["contributors"],
+            serde_json::json!([{ "name": "Jane Doe", "login": "jdoe", "first_time": true }])
+        );
+    }
+
+    #[test]
+    fn test_release_notes_json_schema() {
+        let updates = vec![
+            DependencyUpdate::new("lib", "1.1.0", "2.0.0", Some(12)),
+            DependencyUpdate::new("lib", "1.0.0", "1.1.0", Some(11)),
+        ];
+        let mut notes = notes(updates, &["Fix something"]);
+        notes.range = RangeInfo {
+            from: "v1.0.0".to_string(),
+            to: "HEAD".to_string(),
+            commit_count: 3,
+        };
+
+        let json = serde_json::to_value(&notes).unwrap();
+        assert_eq!(json["range"]["from"], "v1.0.0");
+        assert_eq!(json["range"]["commit_count"], 3);
+        assert_eq!(json["changes"].as_array().unwrap().len(), 3);
+        assert_eq!(json["changes"][2]["subject"], "Fix something");
+        assert_eq!(json["changes"][2]["category"], "other");
+        assert!(json["changes"][2]["pr_number"].is_null());
+        assert_eq!(
+            json["dependency_updates"],
+            serde_json::json!([{
+                "package": "lib",
+                "from": "1.0.0",
+                "to": "2.0.0",
+                "pr_numbers": [12, 11],
+                "major": true,
+                "kind": "major",
+                "downgrade": false,
+                "lockfile": false
+            }])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_conventional_commit_categorized() {
+        let res = process_commit("feat(api): add search (#12)", "", "sha", "User", None)
+            .await
+            .unwrap();
+        let change = res.change();
+        assert_eq!(change.category, conventional::CATEGORY_FEATURES);
+        assert_eq!(change.subject, "add search");
+        assert_eq!(change.scope.as_deref(), Some("api"));
+        assert_eq!(
+            res.lines(Some('#')),
+            vec!["- **api:** add search (#12) (User)"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_conventional_commit_uncategorized_type_kept_in_other() {
+        let res = process_commit("chore: tidy up", "", "sha", "User", None)
+            .await
+            .unwrap();
+        assert_eq!(res.change().category, CATEGORY_OTHER);
+        assert_eq!(res.lines(None), vec!["- chore: tidy up (User)"]);
+    }
+
+    #[tokio::test]
+    async fn test_conventional_commit_breaking_footer() {
+        let body = "BREAKING CHANGE: the old flag is gone";
+        let res = process_commit("fix: rename flag", body, "sha", "User", None)
+            .await
+            .unwrap();
+        assert!(res.change().breaking);
+    }
+
+    #[test]
+    fn test_generate_release_notes_conventional_sections() {
+        let mut breaking = change("drop v1 API", conventional::CATEGORY_FEATURES);
+        breaking.breaking = true;
+        let commits = vec![
+            ProcessedCommit::Other(change("Something else", CATEGORY_OTHER)),
+            ProcessedCommit::Other(change("faster startup", conventional::CATEGORY_PERFORMANCE)),
+            ProcessedCommit::Other(change("fix crash", conventional::CATEGORY_FIXES)),
+            ProcessedCommit::Other(change("add search", conventional::CATEGORY_FEATURES)),
+            ProcessedCommit::Other(breaking),
+        ];
+        let output = generate_release_notes(
+            &build_release_notes(RangeInfo::default(), commits),
+            None,
+            &Config::default(),
+        );
+
+        assert_eq!(
+            output,
+            [
+                "## ⚠ Breaking changes:",
+                "- drop v1 API (User)",
+                "",
+                "## Features:",
+                "- add search (User)",
+                "",
+                "## Bug fixes:",
+                "- fix crash (User)",
+                "",
+                "## Performance:",
+                "- faster startup (User)",
+                "",
+                "## Other changes:",
+                "- Something else (User)",
+            ]
+            .join("\n")
+        );
     }
 }
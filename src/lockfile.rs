@@ -0,0 +1,455 @@
+//! Dependency changes read from lockfiles, so upgrades made by hand show up next to the bots'
+//!
+//! The lockfiles at both ends of the range are compared package by package: `Cargo.lock`,
+//! `package-lock.json`, `yarn.lock`, `go.sum` and the dependencies declared in `pom.xml`.
+
+use anyhow::Result;
+use gix::{ObjectId, Repository};
+use regex::Regex;
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::model::DependencyUpdate;
+use crate::version::Version;
+
+/// Lockfile names and the ecosystem of their packages, as Dependabot names it
+const LOCKFILES: [(&str, &str); 5] = [
+    ("Cargo.lock", "cargo"),
+    ("package-lock.json", "npm"),
+    ("yarn.lock", "npm"),
+    ("go.sum", "gomod"),
+    ("pom.xml", "maven"),
+];
+
+/// Versions of each package a lockfile pins; there may be several, e.g. in `Cargo.lock`
+type Pinned = BTreeMap<String, BTreeSet<String>>;
+
+/// Parse a lockfile by its file name; `None` for other files, or contents that can't be parsed
+fn parse(file_name: &str, content: &str) -> Option<Pinned> {
+    match file_name {
+        "Cargo.lock" => parse_cargo_lock(content),
+        "package-lock.json" => parse_package_lock(content),
+        "yarn.lock" => Some(parse_yarn_lock(content)),
+        "go.sum" => Some(parse_go_sum(content)),
+        "pom.xml" => Some(parse_pom(content)),
+        _ => None,
+    }
+}
+
+fn pin(pinned: &mut Pinned, package: &str, version: &str) {
+    if !package.is_empty() && !version.is_empty() {
+        pinned
+            .entry(package.to_string())
+            .or_default()
+            .insert(version.to_string());
+    }
+}
+
+fn parse_cargo_lock(content: &str) -> Option<Pinned> {
+    let lock: toml::Value = toml::from_str(content).ok()?;
+    let mut pinned = Pinned::new();
+    for package in lock.get("package")?.as_array()? {
+        let field = |key| {
+            package
+                .get(key)
+                .and_then(|v| v.as_str())
+                .unwrap_or_default()
+        };
+        // Packages of the workspace itself have no source
+        if package.get("source").is_some() {
+            pin(&mut pinned, field("name"), field("version"));
+        }
+    }
+    Some(pinned)
+}
+
+fn parse_package_lock(content: &str) -> Option<Pinned> {
+    let lock: serde_json::Value = serde_json::from_str(content).ok()?;
+    let mut pinned = Pinned::new();
+    if let Some(packages) = lock.get("packages").and_then(|p| p.as_object()) {
+        // Lockfile version 2 and 3: keyed by path, "" being the project itself
+        for (path, package) in packages {
+            let Some((_, name)) = path.rsplit_once("node_modules/") else {
+                continue;
+            };
+            if package.get("link").is_none() {
+                let version = package.get("version").and_then(|v| v.as_str());
+                pin(&mut pinned, name, version.unwrap_or_default());
+            }
+        }
+    } else if let Some(dependencies) = lock.get("dependencies") {
+        // Lockfile version 1: nested by dependency
+        let mut pending = vec![dependencies];
+        while let Some(dependencies) = pending.pop() {
+            for (name, package) in dependencies.as_object().into_iter().flatten() {
+                let version = package.get("version").and_then(|v| v.as_str());
+                pin(&mut pinned, name, version.unwrap_or_default());
+                pending.extend(package.get("dependencies"));
+            }
+        }
+    }
+    Some(pinned)
+}
+
+/// Both the classic format (`version "1.2.3"`) and Yarn 2+ (`version: 1.2.3`)
+fn parse_yarn_lock(content: &str) -> Pinned {
+    let re_version = Regex::new(r#"^\s+version:?\s+"?([^"\s]+)"?"#).unwrap();
+    let mut pinned = Pinned::new();
+    let mut package: Option<String> = None;
+    for line in content.lines() {
+        if line.starts_with('#') || line.trim().is_empty() {
+            continue;
+        }
+        if !line.starts_with(' ') {
+            // e.g. `"@babel/core@^7.0.0", "@babel/core@^7.1.0":` or `lodash@npm:^4.17.0:`
+            let spec = line
+                .trim_end_matches(':')
+                .split(", ")
+                .next()
+                .unwrap_or_default();
+            let spec = spec.trim_matches('"');
+            package = spec
+                .rfind('@')
+                .filter(|&at| at > 0)
+                .map(|at| spec[..at].to_string())
+                .filter(|name| name != "__metadata");
+            continue;
+        }
+        if let (Some(name), Some(caps)) = (&package, re_version.captures(line)) {
+            pin(&mut pinned, name, &caps[1]);
+        }
+    }
+    pinned
+}
+
+fn parse_go_sum(content: &str) -> Pinned {
+    let mut pinned = Pinned::new();
+    for line in content.lines() {
+        let mut fields = line.split_whitespace();
+        if let (Some(module), Some(version)) = (fields.next(), fields.next()) {
+            // Modules whose code isn't needed are only listed with their go.mod
+            if !version.ends_with("/go.mod") {
+                pin(&mut pinned, module, version);
+            }
+        }
+    }
+    pinned
+}
+
+/// `groupId:artifactId` of the declared dependencies, with `${property}` versions resolved
+fn parse_pom(content: &str) -> Pinned {
+    let re_dependency = Regex::new(r"(?s)<dependency>(.*?)</dependency>").unwrap();
+    let re_properties = Regex::new(r"(?s)<properties>(.*?)</properties>").unwrap();
+    let re_property = Regex::new(r"(?s)<([\w.-]+)>\s*([^<]*?)\s*</([\w.-]+)>").unwrap();
+    let element = |xml: &str, name: &str| {
+        Regex::new(&format!(r"(?s)<{0}>\s*([^<]*?)\s*</{0}>", name))
+            .unwrap()
+            .captures(xml)
+            .map(|caps| caps[1].to_string())
+    };
+
+    let mut properties = BTreeMap::new();
+    for block in re_properties.captures_iter(content) {
+        for caps in re_property.captures_iter(&block[1]) {
+            if caps[1] == caps[3] {
+                properties.insert(format!("${{{}}}", &caps[1]), caps[2].to_string());
+            }
+        }
+    }
+
+    let mut pinned = Pinned::new();
+    for caps in re_dependency.captures_iter(content) {
+        let dependency = &caps[1];
+        let (Some(group), Some(artifact), Some(version)) = (
+            element(dependency, "groupId"),
+            element(dependency, "artifactId"),
+            element(dependency, "version"),
+        ) else {
+            continue;
+        };
+        let version = properties.get(&version).cloned().unwrap_or(version);
+        // Versions from properties that aren't defined here can't be told apart
+        if !version.contains("${") {
+            pin(&mut pinned, &format!("{}:{}", group, artifact), &version);
+        }
+    }
+    pinned
+}
+
+/// Sorts versions that can be compared by version, others by text
+fn version_order(a: &String, b: &String) -> std::cmp::Ordering {
+    match (Version::parse(a), Version::parse(b)) {
+        (Some(va), Some(vb)) => va.cmp(&vb).then_with(|| a.cmp(b)),
+        _ => a.cmp(b),
+    }
+}
+
+/// Added, removed and changed packages, in order of their names
+///
+/// Added packages have an empty `from`, removed ones an empty `to`. When a package is pinned in
+/// several versions, the removed and added ones are paired up from the lowest.
+fn diff(old: &Pinned, new: &Pinned) -> Vec<DependencyUpdate> {
+    let update = |name: &str, from: &str, to: &str| DependencyUpdate {
+        lockfile: true,
+        ..DependencyUpdate::new(name, from, to, None)
+    };
+    let empty = BTreeSet::new();
+    let names: BTreeSet<&String> = old.keys().chain(new.keys()).collect();
+    let mut updates = Vec::new();
+    for name in names {
+        let before = old.get(name).unwrap_or(&empty);
+        let after = new.get(name).unwrap_or(&empty);
+        let mut removed: Vec<&String> = before.difference(after).collect();
+        let mut added: Vec<&String> = after.difference(before).collect();
+        removed.sort_by(|a, b| version_order(a, b));
+        added.sort_by(|a, b| version_order(a, b));
+
+        let paired = removed.len().min(added.len());
+        for (from, to) in removed.iter().zip(&added) {
+            updates.push(update(name, from, to));
+        }
+        for from in &removed[paired..] {
+            updates.push(update(name, from, ""));
+        }
+        for to in &added[paired..] {
+            updates.push(update(name, "", to));
+        }
+    }
+    updates
+}
+
+/// Contents of the blob at `path` in `tree`, `None` when there is none
+fn read(tree: &gix::Tree<'_>, path: &str) -> Result<Option<String>> {
+    match tree.lookup_entry_by_path(path)? {
+        Some(entry) => {
+            let blob = entry.object()?;
+            Ok(Some(String::from_utf8_lossy(&blob.data).into_owned()))
+        }
+        None => Ok(None),
+    }
+}
+
+/// Dependency changes in the lockfiles that differ between the commits `from` and `to`
+///
+/// Each update is tagged with the lockfile's ecosystem and directory. Lockfiles that can't be
+/// parsed at either end are skipped, as they don't say what changed.
+pub fn lockfile_updates(
+    repo: &Repository,
+    from: ObjectId,
+    to: ObjectId,
+) -> Result<Vec<DependencyUpdate>> {
+    let old_tree = repo.find_commit(from)?.tree()?;
+    let new_tree = repo.find_commit(to)?.tree()?;
+    let mut paths: Vec<String> = repo
+        .diff_tree_to_tree(Some(&old_tree), Some(&new_tree), None)?
+        .iter()
+        .map(|change| change.location().to_string())
+        .collect();
+    paths.sort();
+    paths.dedup();
+
+    let mut updates = Vec::new();
+    for path in paths {
+        let (directory, file_name) = match path.rsplit_once('/') {
+            Some((directory, file_name)) => (Some(format!("/{}", directory)), file_name),
+            None => (None, path.as_str()),
+        };
+        let Some((_, ecosystem)) = LOCKFILES.iter().find(|(name, _)| *name == file_name) else {
+            continue;
+        };
+        let old = read(&old_tree, &path)?.unwrap_or_default();
+        let new = read(&new_tree, &path)?.unwrap_or_default();
+        let parse_or_empty = |content: &str| match content {
+            "" => Some(Pinned::new()),
+            content => parse(file_name, content),
+        };
+        let (Some(old), Some(new)) = (parse_or_empty(&old), parse_or_empty(&new)) else {
+            continue;
+        };
+        for mut update in diff(&old, &new) {
+            update.ecosystem = Some(ecosystem.to_string());
+            update.directory = directory.clone();
+            updates.push(update);
+        }
+    }
+    Ok(updates)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{commit, git, init_repo};
+
+    fn pinned(entries: &[(&str, &str)]) -> Pinned {
+        let mut pinned = Pinned::new();
+        for (package, version) in entries {
+            pin(&mut pinned, package, version);
+        }
+        pinned
+    }
+
+    #[test]
+    fn test_parse_lockfiles() {
+        let cargo_lock = r#"
+version = 3
+
+[[package]]
+name = "app"
+version = "0.1.0"
+
+[[package]]
+name = "syn"
+version = "1.0.109"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+
+[[package]]
+name = "syn"
+version = "2.0.48"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+"#;
+        assert_eq!(
+            parse("Cargo.lock", cargo_lock),
+            Some(pinned(&[("syn", "1.0.109"), ("syn", "2.0.48")]))
+        );
+
+        let package_lock = r#"{
+            "lockfileVersion": 3,
+            "packages": {
+                "": { "name": "app" },
+                "node_modules/@babel/core": { "version": "7.23.9" },
+                "node_modules/a/node_modules/lodash": { "version": "4.17.21" }
+            }
+        }"#;
+        assert_eq!(
+            parse("package-lock.json", package_lock),
+            Some(pinned(&[("@babel/core", "7.23.9"), ("lodash", "4.17.21")]))
+        );
+
+        let yarn_lock = "# yarn lockfile v1\n\n\
+                         \"@babel/core@^7.0.0\", \"@babel/core@^7.1.0\":\n  version \"7.23.9\"\n  \
+                         resolved \"https://registry.yarnpkg.com/x\"\n\n\
+                         lodash@npm:^4.17.0:\n  version: 4.17.21\n";
+        assert_eq!(
+            parse("yarn.lock", yarn_lock),
+            Some(pinned(&[("@babel/core", "7.23.9"), ("lodash", "4.17.21")]))
+        );
+
+        let go_sum = "golang.org/x/text v0.14.0 h1:abc=\n\
+                      golang.org/x/text v0.14.0/go.mod h1:def=\n\
+                      golang.org/x/net v0.19.0/go.mod h1:ghi=\n";
+        assert_eq!(
+            parse("go.sum", go_sum),
+            Some(pinned(&[("golang.org/x/text", "v0.14.0")]))
+        );
+
+        let pom = r#"<project>
+  <properties>
+    <spring.version>6.1.3</spring.version>
+  </properties>
+  <dependencies>
+    <dependency>
+      <groupId>org.springframework</groupId>
+      <artifactId>spring-core</artifactId>
+      <version>${spring.version}</version>
+    </dependency>
+    <dependency>
+      <groupId>junit</groupId>
+      <artifactId>junit</artifactId>
+      <version>4.13.2</version>
+      <scope>test</scope>
+    </dependency>
+    <dependency>
+      <groupId>org.example</groupId>
+      <artifactId>managed</artifactId>
+    </dependency>
+  </dependencies>
+</project>"#;
+        assert_eq!(
+            parse("pom.xml", pom),
+            Some(pinned(&[
+                ("junit:junit", "4.13.2"),
+                ("org.springframework:spring-core", "6.1.3")
+            ]))
+        );
+    }
+
+    #[test]
+    fn test_diff() {
+        let old = pinned(&[("serde", "1.0.1"), ("syn", "1.0.109"), ("old", "0.1.0")]);
+        let new = pinned(&[
+            ("serde", "1.0.2"),
+            ("syn", "1.0.109"),
+            ("syn", "2.0.48"),
+            ("new", "0.2.0"),
+        ]);
+        let lines: Vec<String> = diff(&old, &new).iter().map(|u| u.line(None)).collect();
+        assert_eq!(
+            lines,
+            vec![
+                "- Adds `new` 0.2.0",
+                "- Removes `old` 0.1.0",
+                "- Updates `serde` from 1.0.1 to 1.0.2",
+                "- Adds `syn` 2.0.48",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_lockfile_updates_between_commits() {
+        let dir = init_repo();
+        let path = dir.path();
+        std::fs::create_dir(path.join("backend")).unwrap();
+        let write_go_sum = |version: &str| {
+            std::fs::write(
+                path.join("go.sum"),
+                format!("golang.org/x/text {} h1:abc=\n", version),
+            )
+            .unwrap();
+        };
+        write_go_sum("v0.13.0");
+        std::fs::write(
+            path.join("backend/yarn.lock"),
+            "lodash@^4.17.0:\n  version \"4.17.20\"\n",
+        )
+        .unwrap();
+        git(path, &["add", "."]);
+        let from = commit(path, "Initial commit");
+
+        write_go_sum("v0.14.0");
+        std::fs::write(
+            path.join("backend/yarn.lock"),
+            "lodash@^4.17.0:\n  version \"4.17.21\"\n",
+        )
+        .unwrap();
+        std::fs::write(path.join("README.md"), "Docs").unwrap();
+        git(path, &["add", "."]);
+        let to = commit(path, "Upgrade dependencies");
+
+        let repo = gix::open(path).unwrap();
+        let updates = lockfile_updates(&repo, from, to).unwrap();
+        let summary: Vec<(String, Option<String>, String)> = updates
+            .iter()
+            .map(|u| {
+                (
+                    u.ecosystem.clone().unwrap(),
+                    u.directory.clone(),
+                    u.line(None),
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (
+                    "npm".to_string(),
+                    Some("/backend".to_string()),
+                    "- Updates `lodash` from 4.17.20 to 4.17.21".to_string()
+                ),
+                (
+                    "gomod".to_string(),
+                    None,
+                    "- Updates `golang.org/x/text` from v0.13.0 to v0.14.0".to_string()
+                ),
+            ]
+        );
+    }
+}
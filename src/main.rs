@@ -1,12 +1,33 @@
 use anyhow::{anyhow, Context, Result};
 use arboard::Clipboard;
-use clap::Parser;
-use git_releasenotes::{generate_release_notes, process_commit_with_pr, ProcessedCommit};
-use octocrab::Octocrab;
+use clap::{Parser, Subcommand, ValueEnum};
+use futures::stream::{self, StreamExt};
+use git_releasenotes::cache::{default_cache_dir, Cached};
+use git_releasenotes::changelog::{
+    insert_release, parse_release_date, render_section, version_from_tag, CompareLinks,
+    CHANGELOG_FILE_NAME,
+};
+use git_releasenotes::contributors::contributors;
+use git_releasenotes::forge::{
+    Bitbucket, Forge, ForgeKind, GitHub, GitLab, Gitea, Prefetched, RemoteRepo,
+};
+use git_releasenotes::lockfile::lockfile_updates;
+use git_releasenotes::publish::{publish_release, Published, ReleaseOptions};
+use git_releasenotes::range::{commits_in_range, parse_range, previous_tag, resolve_commit};
+use git_releasenotes::release_yml::ReleaseYml;
+use git_releasenotes::remote::{default_branch, remote_url, DEFAULT_BRANCH, DEFAULT_REMOTE};
+use git_releasenotes::{
+    add_lockfile_updates, build_release_notes, collapse_pull_requests, generate_release_notes,
+    process_commit_with_pr, Config, RangeInfo, Warnings,
+};
 use regex::Regex;
 use std::env;
+use std::path::PathBuf;
 use std::process::Command;
 
+/// Commits processed at the same time when not configured
+const DEFAULT_CONCURRENCY: usize = 8;
+
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 struct Args {
@@ -37,6 +58,120 @@ struct Args {
     /// Specify a commit hash to use instead of tag
     #[arg(short = 'C', conflicts_with = "tag")]
     commit: Option<String>,
+
+    /// Start of the range (tag, branch or commit) instead of the latest tag
+    #[arg(long, conflicts_with_all = ["tag", "commit"])]
+    from: Option<String>,
+
+    /// End of the range (tag, branch or commit) instead of HEAD, nothing is checked out
+    #[arg(long)]
+    to: Option<String>,
+
+    /// Range as FROM..TO, e.g. v1.2.0..release/1.3
+    #[arg(long, conflicts_with_all = ["tag", "commit", "from", "to"])]
+    range: Option<String>,
+
+    /// Output format [default: markdown]
+    #[arg(long, value_enum)]
+    format: Option<OutputFormat>,
+
+    /// Branch that releases are cut from [default: the remote's HEAD, or main]
+    #[arg(long)]
+    branch: Option<String>,
+
+    /// Remote to fetch tags and pull from [default: origin]
+    #[arg(long)]
+    remote: Option<String>,
+
+    /// Read the repository as-is: no fetch, checkout or pull, and no check for local changes
+    #[arg(long)]
+    no_sync: bool,
+
+    /// Like --no-sync, and also skip all GitHub API lookups
+    #[arg(long)]
+    offline: bool,
+
+    /// GitHub API root for GitHub Enterprise Server, e.g. https://github.example.com/api/v3
+    /// [default: $GITHUB_API_URL, or derived from the remote's host]
+    #[arg(long)]
+    github_api_url: Option<String>,
+
+    /// Number of commits whose PRs are looked up at the same time [default: 8]
+    #[arg(long)]
+    concurrency: Option<usize>,
+
+    /// Don't read or write the cache of PR lookups
+    #[arg(long)]
+    no_cache: bool,
+
+    /// Look up all PRs again and update the cache with the results
+    #[arg(long, conflicts_with = "no_cache")]
+    refresh_cache: bool,
+
+    /// List each PR once, by its title and author, instead of the subjects of its commits
+    #[arg(long)]
+    pr_titles: bool,
+
+    /// Fail when any PR lookup failed, e.g. because of an expired token or rate limits
+    #[arg(long)]
+    strict: bool,
+
+    /// Config file to use instead of .releasenotes.toml at the repository root
+    #[arg(long)]
+    config: Option<PathBuf>,
+
+    #[command(subcommand)]
+    action: Option<Action>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Action {
+    /// Add the notes as a release section to CHANGELOG.md, in Keep a Changelog format
+    Changelog {
+        /// Version of the release [default: taken from --to when it is a version tag]
+        #[arg(long = "version")]
+        release_version: Option<String>,
+
+        /// Release date as YYYY-MM-DD [default: today]
+        #[arg(long, value_parser = parse_release_date)]
+        date: Option<String>,
+
+        /// Changelog to update [default: CHANGELOG.md at the repository root]
+        #[arg(long)]
+        file: Option<PathBuf>,
+    },
+    /// Create or update the GitHub Release for a tag with the notes as its body
+    Publish {
+        /// Tag of the release [default: --to]
+        #[arg(long)]
+        tag: Option<String>,
+
+        /// Save the release as a draft
+        #[arg(long)]
+        draft: bool,
+
+        /// Mark the release as a pre-release
+        #[arg(long)]
+        prerelease: bool,
+    },
+}
+
+/// What is needed from a commit to process it, read up front
+struct CommitInfo {
+    subject: String,
+    body: String,
+    author: String,
+    hash: String,
+    pr_from_merge: Option<u64>,
+    paths: Vec<String>,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+enum OutputFormat {
+    /// Markdown sections, as pasted into a release
+    Markdown,
+    /// Structured JSON with changes, dependency updates and range metadata
+    Json,
 }
 
 fn debug(msg: &str, debug_mode: bool) {
@@ -62,22 +197,38 @@ fn run_git(args: &[&str]) -> Result<String> {
     Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
 }
 
-#[tokio::main]
-async fn main() -> Result<()> {
-    // Load .env file if it exists
-    dotenv::dotenv().ok();
-
-    let args = Args::parse();
-
-    // Open repo
-    let repo = gix::discover(".").context("Failed to discover git repository")?;
+/// Paths touched by a commit, compared to its first parent
+fn changed_paths(repo: &gix::Repository, commit: &gix::Commit<'_>) -> Result<Vec<String>> {
+    let tree = commit.tree()?;
+    let parent_tree = match commit.parent_ids().next() {
+        Some(parent_id) => Some(parent_id.object()?.into_commit().tree()?),
+        None => None,
+    };
+    let changes = repo.diff_tree_to_tree(parent_tree.as_ref(), &tree, None)?;
+    Ok(changes
+        .iter()
+        .map(|change| change.location().to_string())
+        .collect())
+}
 
+/// Make sure the release branch is checked out and up to date with the remote
+///
+/// Refuses to run with local changes unless `terse`, in which case git failures are ignored.
+/// `update_branch` is false when the range is given explicitly, since that is read straight
+/// from the object database; only tags are fetched then, and local changes don't matter.
+fn sync_repository(
+    repo: &gix::Repository,
+    remote: &str,
+    main_branch: &str,
+    terse: bool,
+    update_branch: bool,
+) -> Result<()> {
     // Check if git CLI is available (for fetch/pull which are complex in gix)
     if Command::new("git").arg("--version").output().is_err() {
         return Err(anyhow!("Error: git is not installed or not in PATH"));
     }
 
-    if !args.terse {
+    if !terse && update_branch {
         // Check for local changes
         // Using git CLI for diff check as it's robust for 'dirty' check
         let diff_exit = Command::new("git")
@@ -98,13 +249,12 @@ async fn main() -> Result<()> {
     }
 
     // Fetch tags
-    if args.terse {
-        let _ = run_git(&["fetch", "origin", "--tags"]);
+    if terse {
+        let _ = run_git(&["fetch", remote, "--tags"]);
     } else {
-        run_git(&["fetch", "origin", "--tags"])?;
+        run_git(&["fetch", remote, "--tags"])?;
     }
 
-    let main_branch = "main";
     // Get current branch
     let head_ref = repo.head()?;
     let current_branch = head_ref
@@ -116,80 +266,141 @@ async fn main() -> Result<()> {
         .strip_prefix("refs/heads/")
         .unwrap_or(&current_branch);
 
-    if current_branch_short != main_branch {
-        if args.terse {
-            let _ = run_git(&["checkout", main_branch]);
+    if update_branch {
+        if current_branch_short != main_branch {
+            if terse {
+                let _ = run_git(&["checkout", main_branch]);
+            } else {
+                run_git(&["checkout", main_branch])?;
+            }
+        }
+
+        if terse {
+            let _ = run_git(&["pull", "--ff-only", remote, main_branch]);
         } else {
-            run_git(&["checkout", main_branch])?;
+            run_git(&["pull", "--ff-only", remote, main_branch])?;
         }
     }
 
-    if args.terse {
-        let _ = run_git(&["pull", "--ff-only", "origin", main_branch]);
-    } else {
-        run_git(&["pull", "--ff-only", "origin", main_branch])?;
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    // Load .env file if it exists
+    dotenv::dotenv().ok();
+
+    let mut args = Args::parse();
+
+    // Open repo
+    let repo = gix::discover(".").context("Failed to discover git repository")?;
+
+    let mut config = Config::discover(args.config.as_deref(), repo.workdir())?;
+    config.pr_titles |= args.pr_titles;
+    args.clipboard |= config.defaults.clipboard;
+    args.include_pr_numbers |= config.defaults.include_pr_numbers;
+    args.terse |= config.defaults.terse;
+    let format = match (args.format, &config.defaults.format) {
+        (Some(format), _) => format,
+        (None, Some(name)) => OutputFormat::from_str(name, true)
+            .map_err(|_| anyhow!("Invalid format '{}' in config file", name))?,
+        (None, None) => OutputFormat::Markdown,
+    };
+
+    let concurrency = args
+        .concurrency
+        .or(config.defaults.concurrency)
+        .unwrap_or(DEFAULT_CONCURRENCY)
+        .max(1);
+
+    if let Some(spec) = &args.range {
+        let (from, to) = parse_range(spec)?;
+        args.from = from;
+        args.to = to;
+    }
+
+    // Offline implies no sync, as fetch and pull need the network
+    args.offline |= config.defaults.offline;
+    args.no_sync |= config.defaults.no_sync || args.offline;
+    args.strict |= config.defaults.strict;
+    // --refresh-cache asks for the cache, which wins over the config's default
+    args.no_cache = (args.no_cache || config.defaults.no_cache) && !args.refresh_cache;
+
+    let remote = args
+        .remote
+        .clone()
+        .or_else(|| config.remote.clone())
+        .unwrap_or_else(|| DEFAULT_REMOTE.to_string());
+    let main_branch = args
+        .branch
+        .clone()
+        .or_else(|| config.branch.clone())
+        .or_else(|| default_branch(&repo, &remote))
+        .unwrap_or_else(|| DEFAULT_BRANCH.to_string());
+    debug(
+        &format!("Using branch {} from remote {}", main_branch, remote),
+        args.debug_mode,
+    );
+
+    if !args.no_sync {
+        let explicit_range = args.to.is_some() || args.range.is_some();
+        sync_repository(&repo, &remote, &main_branch, args.terse, !explicit_range)?;
     }
 
     // Re-discover repo after pull/checkout potentially changed things?
     // Usually safe to keep using 'repo' handle, but head might have moved.
     let repo = gix::discover(".")?;
 
-    let from_ref_oid = if let Some(commit_sha) = &args.commit {
-        let obj = repo.rev_parse_single(commit_sha.as_str())?;
-        obj
+    // Publishing a tag that exists is about the commits up to it, as if it was given with --to
+    if let Some(Action::Publish { tag: Some(tag), .. }) = &args.action {
+        let tag_exists = repo.find_reference(&format!("refs/tags/{}", tag)).is_ok();
+        if args.to.is_none() && tag_exists {
+            args.to = Some(tag.clone());
+        }
+    }
+
+    let to_ref = args.to.clone().unwrap_or_else(|| "HEAD".to_string());
+    let to_oid = resolve_commit(&repo, &to_ref)?;
+
+    // GitHub's release notes configuration as of the end of the range, like GitHub reads it
+    if config.use_release_yml {
+        if let Some(release_yml) = ReleaseYml::from_commit(&repo.find_commit(to_oid)?)? {
+            debug("Applying .github/release.yml", args.debug_mode);
+            release_yml.apply(&mut config);
+        }
+    }
+
+    let (from_oid, display_ref) = if let Some(from) = args.from.as_ref().or(args.commit.as_ref()) {
+        (resolve_commit(&repo, from)?, from.clone())
     } else if let Some(tag_name) = &args.tag {
         let tag_ref_name = format!("refs/tags/{}", tag_name);
         let tag_ref = repo
             .find_reference(&tag_ref_name)
             .map_err(|_| anyhow!("Error: '{}' exists but is not a tag", tag_name))?;
-        tag_ref
+        let oid = tag_ref
             .into_fully_peeled_id()
             .context("Failed to peel tag")?
+            .detach();
+        (oid, tag_name.clone())
     } else {
         // Find latest tag
-        // gix doesn't have a direct "describe --tags" equivalent built-in simply yet
-        // We can iterate tags and find the one reachable from HEAD that is closest?
-        // For now, to be safe and concise, falling back to git describe or implementing a simple walk.
-        // Let's stick to git describe for the *logic* of "latest tag" as it's complex to replicate exactly.
-        match run_git(&["describe", "--tags", "--abbrev=0"]) {
-            Ok(tag) => {
-                let tag_ref_name = format!("refs/tags/{}", tag);
-                let tag_ref = repo
-                    .find_reference(&tag_ref_name)
-                    .map_err(|_| anyhow!("Error resolving found tag {}", tag))?;
-                tag_ref
-                    .into_fully_peeled_id()
-                    .context("Failed to peel tag")?
-            }
-            Err(_) => {
+        match previous_tag(&repo, to_oid, args.to.is_some())? {
+            Some(tag) => (resolve_commit(&repo, &tag)?, tag),
+            None => {
                 debug("Error finding latest tag", args.debug_mode);
                 return Err(anyhow!("Error: No tags found in repository"));
             }
         }
     };
 
-    let from_oid = from_ref_oid.object()?.id;
-    let head_oid = repo.head()?.into_peeled_id().context("HEAD not found")?;
-
-    // Commit count
-    // Walk from HEAD to from_oid
-    // Efficient way:
-    let walk = repo.rev_walk([head_oid]).all()?;
-    let mut commit_ids = Vec::new();
-    for res in walk {
-        let info = res?;
-        let oid = info.id;
-        if oid == from_oid {
-            break;
-        }
-        commit_ids.push(oid);
-    }
+    // Commits reachable from the end of the range but not from its start
+    let commit_ids = commits_in_range(&repo, Some(from_oid), to_oid)?;
 
     let commit_count = commit_ids.len();
     debug(
         &format!(
-            "Found {} commits between {} and HEAD",
-            commit_count, from_oid
+            "Found {} commits between {} and {}",
+            commit_count, from_oid, to_ref
         ),
         args.debug_mode,
     );
@@ -209,20 +420,11 @@ async fn main() -> Result<()> {
         return Ok(());
     }
 
-    // Display ref name
-    let display_ref = if let Some(t) = &args.tag {
-        t.clone()
-    } else if let Some(c) = &args.commit {
-        c.clone()
-    } else {
-        // We resolved from describe
-        match run_git(&["describe", "--tags", "--abbrev=0"]) {
-            Ok(t) => t,
-            Err(_) => from_oid.to_string(),
-        }
-    };
+    // JSON output is meant for machines, so skip the human-readable headers, and so are
+    // the subcommands, which don't print the notes at all
+    let terse = args.terse || format == OutputFormat::Json || args.action.is_some();
 
-    if !args.terse {
+    if !terse {
         println!();
         println!("Siste release: {}", display_ref);
         println!();
@@ -230,36 +432,109 @@ async fn main() -> Result<()> {
         println!("----------------------------------------");
     }
 
-    // GitHub client setup
-    let token = env::var("GITHUB_TOKEN").ok();
-    let octocrab = if let Some(t) = token {
-        Octocrab::builder().personal_token(t).build().ok()
-    } else {
-        None
-    };
-
     // Remote URL
-    let remote = repo.find_remote("origin").ok();
-    let remote_url = remote
-        .and_then(|r| {
-            r.url(gix::remote::Direction::Fetch)
-                .map(|u| u.to_bstring().to_string())
-        })
-        .or_else(|| run_git(&["remote", "get-url", "origin"]).ok())
+    let remote_url = remote_url(&repo, &remote)
+        .or_else(|| run_git(&["remote", "get-url", &remote]).ok())
         .unwrap_or_default();
+    let remote_repo = RemoteRepo::parse(&remote_url);
+
+    let github_api_url = args
+        .github_api_url
+        .clone()
+        .or_else(|| {
+            env::var("GITHUB_API_URL")
+                .ok()
+                .filter(|url| !url.is_empty())
+        })
+        .or_else(|| config.github_api_url.clone());
+    // A GitHub API URL means GitHub, even on a host whose name doesn't say so
+    let forge_kind = config
+        .forge
+        .or_else(|| {
+            remote_repo
+                .as_ref()
+                .and_then(|remote| ForgeKind::detect(&remote.host))
+        })
+        .or(github_api_url.as_ref().map(|_| ForgeKind::GitHub));
+    // GitLab refers to merge requests as !123, #123 would link to an issue
+    let pr_sigil = args
+        .include_pr_numbers
+        .then(|| forge_kind.map_or('#', ForgeKind::reference_sigil));
+    // GitHub Enterprise Server serves its API under /api/v3 on the same host
+    let github_api_url = github_api_url.or_else(|| match (forge_kind, &remote_repo) {
+        (Some(ForgeKind::GitHub), Some(remote)) => GitHub::api_url_for_host(&remote.host),
+        _ => None,
+    });
+    debug(
+        &format!(
+            "Remote {} is hosted on {:?}, GitHub API at {}",
+            remote_url,
+            forge_kind,
+            github_api_url.as_deref().unwrap_or("api.github.com")
+        ),
+        args.debug_mode,
+    );
 
-    let repo_regex = Regex::new(r"github\.com[:/]([^/]+)/([^/\.]+)(\.git)?").unwrap();
-    let (owner, repo_name) = if let Some(caps) = repo_regex.captures(&remote_url) {
-        (
-            caps.get(1)
-                .map(|m| m.as_str().to_string())
-                .unwrap_or_default(),
-            caps.get(2)
-                .map(|m| m.as_str().to_string())
-                .unwrap_or_default(),
-        )
-    } else {
-        (String::new(), String::new())
+    // GitHub client setup
+    let token = env::var("GITHUB_TOKEN").ok().filter(|_| !args.offline);
+    let octocrab = match &token {
+        Some(t) => Some(GitHub::client(t, github_api_url.as_deref())?),
+        None => None,
+    };
+
+    let forge: Option<Box<dyn Forge>> = match (forge_kind, &remote_repo) {
+        _ if args.offline => None,
+        (Some(ForgeKind::GitHub), Some(remote)) => match (&octocrab, &token) {
+            (Some(client), Some(token)) => {
+                let (owner, name) = remote.owner_and_name();
+                let mut github = GitHub::new(client.clone(), owner, name);
+                if let Some(api_url) = &github_api_url {
+                    let graphql_url = GitHub::graphql_base_url(api_url);
+                    github = github.with_graphql_client(GitHub::client(token, Some(&graphql_url))?);
+                }
+                Some(Box::new(github) as Box<dyn Forge>)
+            }
+            _ => None,
+        },
+        (Some(ForgeKind::GitLab), Some(remote)) => Some(Box::new(GitLab::new(
+            &GitLab::api_url_for_host(&remote.host),
+            &remote.path,
+            env::var("GITLAB_TOKEN").ok(),
+        )?)),
+        (Some(ForgeKind::Gitea), Some(remote)) => Some(Box::new(Gitea::new(
+            &Gitea::api_url_for_host(&remote.host),
+            &remote.path,
+            env::var("GITEA_TOKEN").ok(),
+        )?)),
+        (Some(ForgeKind::Bitbucket), Some(remote)) => {
+            match Bitbucket::api_url_for_host(&remote.host) {
+                Some(api_url) => Some(Box::new(Bitbucket::new(
+                    &api_url,
+                    &remote.path,
+                    env::var("BITBUCKET_TOKEN").ok(),
+                )?)),
+                None => {
+                    debug(
+                        &format!("No API for Bitbucket Data Center at {}", remote.host),
+                        args.debug_mode,
+                    );
+                    None
+                }
+            }
+        }
+        _ => None,
+    };
+    // Lookups are cached per repository, so the same PRs aren't fetched on every run
+    let cache_dir = default_cache_dir().filter(|_| !args.no_cache);
+    let forge = match (forge, &remote_repo, cache_dir) {
+        (Some(forge), Some(remote), Some(dir)) => {
+            debug(
+                &format!("Caching PR lookups in {}", dir.display()),
+                args.debug_mode,
+            );
+            Some(Box::new(Cached::new(forge, &dir, remote, args.refresh_cache)) as Box<dyn Forge>)
+        }
+        (forge, _, _) => forge,
     };
 
     // Build a map of commit OID -> PR number by scanning merge commits
@@ -267,7 +542,10 @@ async fn main() -> Result<()> {
     // We need to scan a wider range to find merge commits that reference commits in our range
     use std::collections::HashMap;
     let mut commit_to_pr: HashMap<gix::ObjectId, u64> = HashMap::new();
-    let re_merge_pr = Regex::new(r"Merge pull request #([0-9]+)").unwrap();
+    // GitHub and Gitea write "Merge pull request #12 ..." and "Merge pull request 'title' (#12)
+    // ...", Bitbucket "Merged in branch (pull request #12)"
+    let re_merge_pr =
+        Regex::new(r"(?:Merge pull request (?:'.*' \()?#|\(pull request #)([0-9]+)").unwrap();
 
     // First, scan commits in our range for merge commits
     for oid in &commit_ids {
@@ -362,43 +640,226 @@ async fn main() -> Result<()> {
         }
     }
 
-    let mut dependabot_updates = Vec::new();
-    let mut other_changes = Vec::new();
+    // Failed lookups are collected and reported together instead of failing the whole run
+    let warnings = Warnings::default();
+
+    // Look up the PRs of all commits not merged through a PR merge commit in bulk, so
+    // processing only needs single lookups for commits the bulk lookup couldn't cover
+    let prefetched = match &forge {
+        Some(forge) => {
+            let shas: Vec<String> = commit_ids
+                .iter()
+                .filter(|oid| !commit_to_pr.contains_key(*oid))
+                .map(|oid| oid.to_string())
+                .collect();
+            let prefetched = Prefetched::new(forge.as_ref(), &shas, &warnings).await;
+            debug(
+                &format!(
+                    "Looked up PRs of {} of {} commits in bulk",
+                    prefetched.len(),
+                    shas.len()
+                ),
+                args.debug_mode,
+            );
+            Some(prefetched)
+        }
+        None => None,
+    };
 
-    for oid in commit_ids {
+    // Read everything needed from the repository first, so the API lookups can run concurrently
+    let mut commits = Vec::new();
+    for &oid in &commit_ids {
         let obj = repo.find_object(oid)?;
         let commit = obj.into_commit();
         let msg = commit.message()?;
-        let subject = msg.summary().to_string();
-        let body = msg.body().map(|b| b.to_string()).unwrap_or_default();
-        let author = commit.author()?.name.to_string();
-        let hash = oid.to_string();
-
-        // Check if we found a PR number for this commit from merge commits
-        let pr_from_merge = commit_to_pr.get(&oid).copied();
-
-        let result = process_commit_with_pr(
-            &subject,
-            &body,
-            &hash,
-            &author,
-            args.include_pr_numbers,
-            pr_from_merge,
-            &octocrab,
-            &owner,
-            &repo_name,
-        )
+
+        // Only diff trees when a category actually matches on paths
+        let paths = if config.uses_paths() {
+            changed_paths(&repo, &commit)?
+        } else {
+            Vec::new()
+        };
+
+        commits.push(CommitInfo {
+            subject: msg.summary().to_string(),
+            body: msg.body().map(|b| b.to_string()).unwrap_or_default(),
+            author: commit.author()?.name.to_string(),
+            hash: oid.to_string(),
+            // Check if we found a PR number for this commit from merge commits
+            pr_from_merge: commit_to_pr.get(&oid).copied(),
+            paths,
+        });
+    }
+
+    // `buffered` runs up to `concurrency` commits at a time but yields them in commit order
+    let forge_ref = prefetched.as_ref().map(|p| p as &dyn Forge);
+    let config_ref = &config;
+    let warnings_ref = &warnings;
+    let mut processed: Vec<_> = stream::iter(&commits)
+        .map(|commit| {
+            process_commit_with_pr(
+                &commit.subject,
+                &commit.body,
+                &commit.hash,
+                &commit.author,
+                commit.pr_from_merge,
+                config_ref,
+                &commit.paths,
+                forge_ref,
+                warnings_ref,
+            )
+        })
+        .buffered(concurrency)
+        .filter_map(|result| async move { result })
+        .collect()
         .await;
-        if let Some(res) = result {
-            match res {
-                ProcessedCommit::Dependabot(lines) => dependabot_updates.extend(lines),
-                ProcessedCommit::Other(line) => other_changes.push(line),
+
+    // On stderr, so the notes on stdout stay clean
+    if let Some(summary) = warnings.summary() {
+        eprintln!("{}", summary);
+        if args.strict {
+            return Err(anyhow!(
+                "Error: {} PR lookups failed and --strict is set",
+                warnings.len()
+            ));
+        }
+    }
+
+    // Logins are taken before PRs are collapsed, which leaves only one of their commits
+    let contributors = if config.contributors {
+        let logins: HashMap<String, String> = processed
+            .iter()
+            .filter_map(|c| Some((c.change().hash.clone(), c.change().login.clone()?)))
+            .collect();
+        contributors(&repo, &commit_ids, Some(from_oid), &logins, &config)?
+    } else {
+        Vec::new()
+    };
+
+    if config.pr_titles {
+        processed = collapse_pull_requests(processed);
+    }
+
+    let range = RangeInfo {
+        from: display_ref,
+        to: to_ref,
+        commit_count,
+    };
+    let mut notes = build_release_notes(range, processed);
+    notes.contributors = contributors;
+    if config.lockfiles {
+        add_lockfile_updates(&mut notes, lockfile_updates(&repo, from_oid, to_oid)?);
+    }
+
+    if let Some(Action::Changelog {
+        release_version,
+        date,
+        file,
+    }) = &args.action
+    {
+        let version = match release_version {
+            Some(version) => version.clone(),
+            None => args
+                .to
+                .as_deref()
+                .and_then(version_from_tag)
+                .ok_or_else(|| anyhow!("Error: can't tell the version from --to, use --version"))?,
+        };
+        let date = date
+            .clone()
+            .unwrap_or_else(|| chrono::Local::now().format("%Y-%m-%d").to_string());
+        let path = match file {
+            Some(file) => file.clone(),
+            None => repo
+                .workdir()
+                .map(|dir| dir.join(CHANGELOG_FILE_NAME))
+                .unwrap_or_else(|| PathBuf::from(CHANGELOG_FILE_NAME)),
+        };
+
+        // Compare links need the tag of the new release; without --to it usually isn't
+        // created yet, so it is named like the previous one
+        let links = forge_kind
+            .zip(remote_repo.as_ref())
+            .and_then(|(kind, remote)| kind.compare_base_url(remote))
+            .map(|base_url| CompareLinks {
+                base_url,
+                previous_tag: notes.range.from.clone(),
+                tag: match &args.to {
+                    Some(to) => to.clone(),
+                    None if notes.range.from.starts_with('v') => format!("v{}", version),
+                    None => version.clone(),
+                },
+            });
+
+        let existing = match std::fs::read_to_string(&path) {
+            Ok(content) => Some(content),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+            Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+        };
+        let section = render_section(&notes, &version, &date, pr_sigil);
+        let content = insert_release(existing.as_deref(), &version, &section, links.as_ref());
+        std::fs::write(&path, content)
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        if !args.terse {
+            println!("Added {} to {}", version, path.display());
+        }
+        return Ok(());
+    }
+
+    if let Some(Action::Publish {
+        tag,
+        draft,
+        prerelease,
+    }) = &args.action
+    {
+        let tag = tag
+            .clone()
+            .or_else(|| args.to.clone())
+            .ok_or_else(|| anyhow!("Error: no tag to publish, use --to or publish --tag"))?;
+        let octocrab = octocrab.as_ref().ok_or_else(|| {
+            anyhow!("Error: publishing needs GITHUB_TOKEN and can't be combined with --offline")
+        })?;
+        let (owner, repo_name) = match (forge_kind, &remote_repo) {
+            (Some(ForgeKind::GitHub), Some(remote)) => remote.owner_and_name(),
+            _ => {
+                return Err(anyhow!(
+                    "Error: remote '{}' is not a GitHub repository",
+                    remote
+                ))
             }
+        };
+
+        // An empty range would replace the notes of an existing release with nothing
+        if commit_count == 0 {
+            return Err(anyhow!(
+                "Error: no commits between {} and {}, nothing to publish for {}",
+                notes.range.from,
+                notes.range.to,
+                tag
+            ));
         }
+        let body = generate_release_notes(&notes, pr_sigil, &config);
+        let options = ReleaseOptions {
+            draft: *draft,
+            prerelease: *prerelease,
+            target_commitish: Some(to_oid.to_string()),
+        };
+        let published = publish_release(octocrab, owner, repo_name, &tag, &body, &options).await?;
+        if !args.terse {
+            let verb = match published {
+                Published::Created(_) => "Created",
+                Published::Updated(_) => "Updated",
+            };
+            println!("{} release {}: {}", verb, tag, published.release().html_url);
+        }
+        return Ok(());
     }
 
     // Print output
-    let full_output = generate_release_notes(dependabot_updates, other_changes);
+    let full_output = match format {
+        OutputFormat::Markdown => generate_release_notes(&notes, pr_sigil, &config),
+        OutputFormat::Json => serde_json::to_string_pretty(&notes)?,
+    };
 
     if !full_output.is_empty() {
         println!("{}", full_output);
@@ -0,0 +1,338 @@
+use regex::Regex;
+use serde::Serialize;
+
+use crate::contributors::Contributor;
+use crate::ecosystem::Source;
+use crate::version::{UpdateKind, Version};
+
+/// Category key for commits made by dependency bots
+pub const CATEGORY_DEPENDENCIES: &str = "dependencies";
+/// Category key for everything that doesn't fit another category
+pub const CATEGORY_OTHER: &str = "other";
+
+/// A single commit as it appears in the release notes
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Change {
+    /// Commit subject with `(#123)` style PR references removed
+    pub subject: String,
+    pub author: String,
+    pub hash: String,
+    pub pr_number: Option<u64>,
+    pub category: String,
+    /// Conventional Commits scope, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+    /// Marked breaking with `!` or a `BREAKING CHANGE:` footer
+    pub breaking: bool,
+    /// Labels of the PR the commit came from
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub labels: Vec<String>,
+    /// Login of whoever opened the PR the commit came from
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub login: Option<String>,
+    /// Dependency updates parsed from this commit (dependabot only)
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub updates: Vec<DependencyUpdate>,
+}
+
+impl Change {
+    /// Subject as displayed, with the PR number appended when not already present
+    ///
+    /// `pr_sigil` is what the forge writes before the number, `#` or `!` for a GitLab merge
+    /// request; `None` leaves the PR number out.
+    pub fn title(&self, pr_sigil: Option<char>) -> String {
+        let subject = match &self.scope {
+            Some(scope) => format!("**{}:** {}", scope, self.subject),
+            None => self.subject.clone(),
+        };
+        match (pr_sigil, self.pr_number) {
+            (Some(sigil), Some(pr_num)) if !subject.contains(&format!("{}{}", sigil, pr_num)) => {
+                format!("{} ({}{})", subject.trim(), sigil, pr_num)
+            }
+            _ => subject,
+        }
+    }
+}
+
+/// A package going from one version to another, possibly across several PRs
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DependencyUpdate {
+    pub package: String,
+    pub from: String,
+    pub to: String,
+    pub pr_numbers: Vec<u64>,
+    /// Same as `kind` being major, kept for existing consumers of the JSON output
+    pub major: bool,
+    /// How big the version change is; not set when the versions can't be compared
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kind: Option<UpdateKind>,
+    /// Goes to a lower version, e.g. a rollback; never merged with upgrades
+    pub downgrade: bool,
+    /// Only found in a lockfile, no bot made it
+    pub lockfile: bool,
+    /// Package ecosystem as Dependabot names it, e.g. `cargo` or `github-actions`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ecosystem: Option<String>,
+    /// Directory of the manifest, e.g. `/frontend`; not set for the root
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub directory: Option<String>,
+}
+
+impl DependencyUpdate {
+    pub fn new(package: &str, from: &str, to: &str, pr_number: Option<u64>) -> Self {
+        let mut update = DependencyUpdate {
+            package: package.to_string(),
+            from: from.to_string(),
+            to: to.to_string(),
+            pr_numbers: pr_number.into_iter().collect(),
+            major: false,
+            kind: None,
+            downgrade: is_downgrade(from, to),
+            lockfile: false,
+            ecosystem: None,
+            directory: None,
+        };
+        update.classify();
+        update
+    }
+
+    /// Set `kind` and `major` from the versions, after they have changed
+    pub fn classify(&mut self) {
+        self.kind = UpdateKind::classify(&self.from, &self.to);
+        self.major = self.kind == Some(UpdateKind::Major);
+    }
+
+    pub fn source(&self) -> Source {
+        Source {
+            ecosystem: self.ecosystem.clone(),
+            directory: self.directory.clone(),
+        }
+    }
+
+    pub fn set_source(&mut self, source: &Source) {
+        self.ecosystem = source.ecosystem.clone();
+        self.directory = source.directory.clone();
+    }
+
+    /// Both versions, when both can be compared
+    pub fn versions(&self) -> Option<(Version, Version)> {
+        Some((Version::parse(&self.from)?, Version::parse(&self.to)?))
+    }
+
+    /// What happened to the package, e.g. "Updates `pkg` from 1.0 to 1.1"
+    ///
+    /// Packages that were added or removed, as lockfiles tell, have an empty `from` or `to`. A
+    /// bot's update has an empty `from` when it only says where it went, as Renovate's subjects do.
+    pub fn description(&self) -> String {
+        match (self.from.as_str(), self.to.as_str()) {
+            ("", to) if self.lockfile => format!("Adds `{}` {}", self.package, to),
+            ("", to) => format!("Updates `{}` to {}", self.package, to),
+            (from, "") => format!("Removes `{}` {}", self.package, from),
+            (from, to) if self.downgrade => {
+                format!("Downgrades `{}` from {} to {}", self.package, from, to)
+            }
+            (from, to) => format!("Updates `{}` from {} to {}", self.package, from, to),
+        }
+    }
+
+    /// Parse a line like "Updates `pkg` from 1.0 to 1.1 (#123)" or "Bumps [pkg](url) from 1.0 to 1.1"
+    ///
+    /// Renovate's "Update dependency pkg to v1.1", "update rust crate pkg to 1.1" and "Update pkg
+    /// action to v4" are understood as well, leaving `from` empty, and so are the rows of the
+    /// update table in its PR descriptions.
+    pub fn parse(line: &str) -> Option<Self> {
+        if line.trim_start().starts_with('|') {
+            return Self::parse_table_row(line);
+        }
+        let re_update = Regex::new(
+            r"(?i)(?:Updates|Downgrades) `([^`]+)` from ([^ ]+) to ([^ ]+)(?: \((#[0-9]+)\))?",
+        )
+        .unwrap();
+        let re_bump_link = Regex::new(
+            r"(?i)Bumps? \[([^\]]+)\]\([^\)]+\) from ([^ ]+) to ([^ ]+)(?: \((#[0-9]+)\))?",
+        )
+        .unwrap();
+        let re_bump_simple =
+            Regex::new(r"(?i)Bumps? ([^ ]+) from ([^ ]+) to ([^ ]+)(?: \((#[0-9]+)\))?").unwrap();
+        // e.g. "Update dependency pkg from 1.0 to 1.1" or "update rust crate pkg from 1.0 to 1.1"
+        let re_renovate = Regex::new(
+            r"(?i)\bUpdate (?:dependency |(?:[a-z-]+ )?(?:crate|module|package|image|action|orb|plugin) )?([^ ]+) from ([^ ]+) to ([^ ]+)(?: \((#[0-9]+)\))?",
+        )
+        .unwrap();
+        // Renovate's own subjects name the kind of dependency before or after it, but no `from`
+        let re_renovate_to = Regex::new(
+            r"(?i)\bUpdate (?:dependency|(?:[a-z-]+ )?(?:crate|module|package|image|orb|plugin)) ([^ ]+) to ([^ ]+)",
+        )
+        .unwrap();
+        let re_renovate_kind_after =
+            Regex::new(r"(?i)\bUpdate ([^ ]+) (?:[a-z-]+ )?(?:action|tag|digest|image) to ([^ ]+)")
+                .unwrap();
+        let re_pr_number = Regex::new(r"\(#([0-9]+)\)").unwrap();
+
+        let pr_number = re_pr_number
+            .captures(line)
+            .and_then(|caps| caps.get(1))
+            .and_then(|m| m.as_str().parse::<u64>().ok());
+
+        let (package, from, to) = match re_update
+            .captures(line)
+            .or_else(|| re_bump_link.captures(line))
+            .or_else(|| re_bump_simple.captures(line))
+            .or_else(|| re_renovate.captures(line))
+        {
+            Some(caps) => (caps.get(1)?, caps.get(2)?.as_str(), caps.get(3)?),
+            None => {
+                let caps = re_renovate_to
+                    .captures(line)
+                    .or_else(|| re_renovate_kind_after.captures(line))?;
+                (caps.get(1)?, "", caps.get(2)?)
+            }
+        };
+
+        // Dependabot ends the sentence in its PR descriptions after the version
+        Some(DependencyUpdate::new(
+            package.as_str(),
+            from,
+            to.as_str().trim_end_matches('.'),
+            pr_number,
+        ))
+    }
+
+    /// Parse a row of Renovate's table, e.g. "| [pkg](url) ([source](url)) | patch | `1.0` -> `1.1` |"
+    ///
+    /// The package is in the first column and the change in the one with an arrow, so the other
+    /// columns, which depend on the Renovate configuration, don't matter. The change is usually a
+    /// link to the diff, e.g. "[`1.0` -> `1.1`](https://renovatebot.com/diffs/...)".
+    fn parse_table_row(line: &str) -> Option<Self> {
+        let re_link = Regex::new(r"^\[([^\]]+)\]").unwrap();
+        let re_whole_link = Regex::new(r"^\[(.*)\]\([^\)]*\)$").unwrap();
+        let re_change = Regex::new(r"^`?([^`\s]+)`?\s*(?:->|→)\s*`?([^`\s]+)`?$").unwrap();
+
+        let mut cells = line.trim().trim_matches('|').split('|').map(str::trim);
+        let first = cells.next()?;
+        let package = match re_link.captures(first) {
+            Some(caps) => caps.get(1)?.as_str(),
+            None => first.split_whitespace().next()?,
+        };
+        let caps = cells.find_map(|cell| {
+            let cell = re_whole_link
+                .captures(cell)
+                .and_then(|caps| caps.get(1))
+                .map_or(cell, |m| m.as_str().trim());
+            re_change.captures(cell)
+        })?;
+        Some(DependencyUpdate::new(
+            package.trim_matches('`'),
+            caps.get(1)?.as_str(),
+            caps.get(2)?.as_str(),
+            None,
+        ))
+    }
+
+    /// Markdown line for this update; PR numbers are listed highest first, see [`Change::title`]
+    pub fn line(&self, pr_sigil: Option<char>) -> String {
+        let pr_suffix = match pr_sigil {
+            Some(sigil) if !self.pr_numbers.is_empty() => {
+                let mut sorted_prs = self.pr_numbers.clone();
+                sorted_prs.sort();
+                sorted_prs.reverse();
+                let pr_list: Vec<String> = sorted_prs
+                    .iter()
+                    .map(|n| format!("{}{}", sigil, n))
+                    .collect();
+                format!("  ({})", pr_list.join(", "))
+            }
+            _ => String::new(),
+        };
+        format!("- {}{}", self.description(), pr_suffix)
+    }
+}
+
+/// Whether going from `from` to `to` is a breaking change, with Cargo's rules for 0.x versions
+pub fn is_major_change(from: &str, to: &str) -> bool {
+    UpdateKind::classify(from, to) == Some(UpdateKind::Major)
+}
+
+/// Whether `to` is a lower version than `from`; versions that can't be compared aren't
+pub fn is_downgrade(from: &str, to: &str) -> bool {
+    match (Version::parse(from), Version::parse(to)) {
+        (Some(from), Some(to)) => to < from,
+        _ => false,
+    }
+}
+
+/// The commit range the notes were generated for
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct RangeInfo {
+    pub from: String,
+    pub to: String,
+    pub commit_count: usize,
+}
+
+/// Result of processing a single commit
+#[derive(Debug, Clone, PartialEq)]
+pub enum ProcessedCommit {
+    Dependabot(Change),
+    Other(Change),
+}
+
+impl ProcessedCommit {
+    pub fn change(&self) -> &Change {
+        match self {
+            ProcessedCommit::Dependabot(change) | ProcessedCommit::Other(change) => change,
+        }
+    }
+
+    pub fn change_mut(&mut self) -> &mut Change {
+        match self {
+            ProcessedCommit::Dependabot(change) | ProcessedCommit::Other(change) => change,
+        }
+    }
+
+    pub fn into_change(self) -> Change {
+        match self {
+            ProcessedCommit::Dependabot(change) | ProcessedCommit::Other(change) => change,
+        }
+    }
+
+    /// Markdown lines this commit contributes to the notes, before consolidation
+    pub fn lines(&self, pr_sigil: Option<char>) -> Vec<String> {
+        match self {
+            ProcessedCommit::Dependabot(change) if !change.updates.is_empty() => change
+                .updates
+                .iter()
+                .map(|update| {
+                    // Single-commit lines carry the PR number with a single space
+                    match (pr_sigil, change.pr_number) {
+                        (Some(sigil), Some(pr_num)) => {
+                            format!("- {} ({}{})", update.description(), sigil, pr_num)
+                        }
+                        _ => update.line(None),
+                    }
+                })
+                .collect(),
+            ProcessedCommit::Dependabot(change) => vec![format!("- {}", change.title(pr_sigil))],
+            ProcessedCommit::Other(change) => {
+                vec![format!("- {} ({})", change.title(pr_sigil), change.author)]
+            }
+        }
+    }
+}
+
+/// Structured release notes, serialized as-is for `--format json`
+///
+/// Schema:
+/// - `range`: `{ from, to, commit_count }`
+/// - `changes`: every commit in the range as
+///   `{ subject, author, hash, pr_number, category, scope?, breaking, labels?, login?, updates? }`
+/// - `dependency_updates`: consolidated updates as
+///   `{ package, from, to, pr_numbers, major, kind?, downgrade, lockfile, ecosystem?, directory? }`,
+///   by package
+/// - `contributors`: `{ name, login?, first_time }` sorted by name
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ReleaseNotes {
+    pub range: RangeInfo,
+    pub changes: Vec<Change>,
+    pub dependency_updates: Vec<DependencyUpdate>,
+    pub contributors: Vec<Contributor>,
+}
@@ -0,0 +1,231 @@
+use anyhow::{Context, Result};
+use octocrab::models::repos::Release;
+use octocrab::Octocrab;
+
+/// How the GitHub Release is published
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ReleaseOptions {
+    pub draft: bool,
+    pub prerelease: bool,
+    /// Commit the tag is created at when it doesn't exist on GitHub yet
+    pub target_commitish: Option<String>,
+}
+
+/// Result of [`publish_release`], with the release as GitHub returned it
+#[derive(Debug)]
+pub enum Published {
+    Created(Release),
+    Updated(Release),
+}
+
+impl Published {
+    pub fn release(&self) -> &Release {
+        match self {
+            Published::Created(release) | Published::Updated(release) => release,
+        }
+    }
+}
+
+/// The release for `tag`, including drafts
+///
+/// Drafts aren't returned by the get-by-tag endpoint, so the releases are listed instead.
+pub async fn find_release(
+    octocrab: &Octocrab,
+    owner: &str,
+    repo: &str,
+    tag: &str,
+) -> Result<Option<Release>> {
+    let mut page = octocrab
+        .repos(owner, repo)
+        .releases()
+        .list()
+        .per_page(100)
+        .send()
+        .await
+        .with_context(|| format!("Failed to list releases of {}/{}", owner, repo))?;
+    loop {
+        if let Some(release) = page.items.iter().find(|r| r.tag_name == tag) {
+            return Ok(Some(release.clone()));
+        }
+        page = match octocrab.get_page::<Release>(&page.next).await? {
+            Some(next) => next,
+            None => return Ok(None),
+        };
+    }
+}
+
+/// Create the GitHub Release for `tag` with `body` as its notes, or update it in place if it exists
+pub async fn publish_release(
+    octocrab: &Octocrab,
+    owner: &str,
+    repo: &str,
+    tag: &str,
+    body: &str,
+    options: &ReleaseOptions,
+) -> Result<Published> {
+    let releases = octocrab.repos(owner, repo);
+    let releases = releases.releases();
+
+    if let Some(existing) = find_release(octocrab, owner, repo, tag).await? {
+        let release = releases
+            .update(existing.id.into_inner())
+            .body(body)
+            .draft(options.draft)
+            .prerelease(options.prerelease)
+            .send()
+            .await
+            .with_context(|| format!("Failed to update release {}", tag))?;
+        return Ok(Published::Updated(release));
+    }
+
+    let mut create = releases
+        .create(tag)
+        .name(tag)
+        .body(body)
+        .draft(options.draft)
+        .prerelease(options.prerelease);
+    if let Some(target) = &options.target_commitish {
+        create = create.target_commitish(target);
+    }
+    let release = create
+        .send()
+        .await
+        .with_context(|| format!("Failed to create release {}", tag))?;
+    Ok(Published::Created(release))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Value};
+    use wiremock::matchers::{body_partial_json, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn release_json(id: u64, tag: &str, draft: bool) -> Value {
+        let api = "https://api.github.com/repos/owner/repo";
+        json!({
+            "url": format!("{}/releases/{}", api, id),
+            "html_url": format!("https://github.com/owner/repo/releases/tag/{}", tag),
+            "assets_url": format!("{}/releases/{}/assets", api, id),
+            "upload_url": format!("https://uploads.github.com/repos/owner/repo/releases/{}/assets", id),
+            "id": id,
+            "node_id": "RE_node",
+            "tag_name": tag,
+            "target_commitish": "main",
+            "name": tag,
+            "body": "",
+            "draft": draft,
+            "prerelease": false,
+            "assets": []
+        })
+    }
+
+    async fn setup(existing: Vec<Value>) -> (MockServer, Octocrab) {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/repos/owner/repo/releases"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(existing))
+            .expect(1)
+            .mount(&server)
+            .await;
+        let octocrab = Octocrab::builder()
+            .base_uri(server.uri())
+            .unwrap()
+            .build()
+            .unwrap();
+        (server, octocrab)
+    }
+
+    #[tokio::test]
+    async fn test_creates_release() {
+        let (server, octocrab) = setup(vec![release_json(1, "v1.2.0", false)]).await;
+        Mock::given(method("POST"))
+            .and(path("/repos/owner/repo/releases"))
+            .and(body_partial_json(json!({
+                "tag_name": "v1.3.0",
+                "name": "v1.3.0",
+                "body": "## Notes",
+                "draft": true,
+                "prerelease": true,
+                "target_commitish": "abc123"
+            })))
+            .respond_with(ResponseTemplate::new(201).set_body_json(release_json(2, "v1.3.0", true)))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let options = ReleaseOptions {
+            draft: true,
+            prerelease: true,
+            target_commitish: Some("abc123".to_string()),
+        };
+        let published = publish_release(&octocrab, "owner", "repo", "v1.3.0", "## Notes", &options)
+            .await
+            .unwrap();
+        assert!(matches!(published, Published::Created(_)));
+        assert!(published.release().draft);
+    }
+
+    #[tokio::test]
+    async fn test_updates_existing_draft_in_place() {
+        let (server, octocrab) = setup(vec![
+            release_json(2, "v1.3.0", true),
+            release_json(1, "v1.2.0", false),
+        ])
+        .await;
+        Mock::given(method("PATCH"))
+            .and(path("/repos/owner/repo/releases/2"))
+            .and(body_partial_json(
+                json!({ "body": "## New notes", "draft": false }),
+            ))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(release_json(2, "v1.3.0", false)),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(500))
+            .expect(0)
+            .mount(&server)
+            .await;
+
+        let published = publish_release(
+            &octocrab,
+            "owner",
+            "repo",
+            "v1.3.0",
+            "## New notes",
+            &ReleaseOptions::default(),
+        )
+        .await
+        .unwrap();
+        assert!(matches!(published, Published::Updated(_)));
+        assert!(!published.release().draft);
+    }
+
+    #[tokio::test]
+    async fn test_api_error_is_reported() {
+        let (server, octocrab) = setup(vec![]).await;
+        Mock::given(method("POST"))
+            .and(path("/repos/owner/repo/releases"))
+            .respond_with(ResponseTemplate::new(422).set_body_json(json!({
+                "message": "Validation Failed",
+                "documentation_url": "https://docs.github.com/rest"
+            })))
+            .mount(&server)
+            .await;
+
+        let err = publish_release(
+            &octocrab,
+            "owner",
+            "repo",
+            "v1.3.0",
+            "",
+            &ReleaseOptions::default(),
+        )
+        .await
+        .unwrap_err();
+        assert!(err.to_string().contains("Failed to create release v1.3.0"));
+    }
+}
@@ -0,0 +1,291 @@
+use anyhow::{anyhow, Context, Result};
+use gix::{ObjectId, Repository};
+
+/// Split a `FROM..TO` spec into its ends; an empty end means it wasn't given
+pub fn parse_range(spec: &str) -> Result<(Option<String>, Option<String>)> {
+    if spec.contains("...") {
+        return Err(anyhow!(
+            "Symmetric ranges ('{}') are not supported, use FROM..TO",
+            spec
+        ));
+    }
+    let (from, to) = spec
+        .split_once("..")
+        .ok_or_else(|| anyhow!("Invalid range '{}', expected FROM..TO", spec))?;
+
+    let non_empty = |s: &str| Some(s.trim().to_string()).filter(|s| !s.is_empty());
+    Ok((non_empty(from), non_empty(to)))
+}
+
+/// Resolve a tag, branch, commit hash or other revision to the commit it points at
+pub fn resolve_commit(repo: &Repository, spec: &str) -> Result<ObjectId> {
+    let id = repo
+        .rev_parse_single(spec)
+        .with_context(|| format!("Error: could not resolve '{}'", spec))?;
+    let commit = id
+        .object()?
+        .peel_to_commit()
+        .with_context(|| format!("Error: '{}' does not point to a commit", spec))?;
+    Ok(commit.id)
+}
+
+/// Commits reachable from `to` but not from `from`, like `git rev-list from..to`, newest first
+///
+/// Unlike stopping at the first occurrence of `from`, this excludes commits that were already
+/// released through another parent and still finds the boundary on non-linear history.
+pub fn commits_in_range(
+    repo: &Repository,
+    from: Option<ObjectId>,
+    to: ObjectId,
+) -> Result<Vec<ObjectId>> {
+    let walk = repo
+        .rev_walk([to])
+        .with_hidden(from)
+        .sorting(gix::revision::walk::Sorting::ByCommitTime(
+            gix::traverse::commit::simple::CommitTimeOrder::NewestFirst,
+        ))
+        .all()?;
+
+    let mut commits = Vec::new();
+    for info in walk {
+        commits.push(info?.id);
+    }
+    Ok(commits)
+}
+
+/// Name of the closest tag reachable from `commit`, like `git describe --tags --abbrev=0`
+pub fn latest_tag(repo: &Repository, commit: ObjectId) -> Result<Option<String>> {
+    let resolution = repo
+        .find_commit(commit)?
+        .describe()
+        .names(gix::commit::describe::SelectRef::AllTags)
+        .id_as_fallback(false)
+        .try_resolve()?;
+    Ok(resolution.and_then(|r| r.outcome.name.map(|name| name.to_string())))
+}
+
+/// Start of the range ending at `to` when none is given: the closest tag before it
+///
+/// With an explicit end, e.g. `--to v1.3.0` or the tag being published, the search starts from
+/// its parent, so a tag on the end itself yields the previous release instead of an empty range.
+pub fn previous_tag(repo: &Repository, to: ObjectId, explicit_end: bool) -> Result<Option<String>> {
+    let describe_from = if explicit_end {
+        repo.find_commit(to)?
+            .parent_ids()
+            .next()
+            .map(|id| id.detach())
+    } else {
+        Some(to)
+    };
+    match describe_from {
+        Some(oid) => latest_tag(repo, oid),
+        None => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{commit, commit_at, git, init_repo};
+
+    #[test]
+    fn test_parse_range() {
+        assert_eq!(
+            parse_range("v1.2.0..release/1.3").unwrap(),
+            (Some("v1.2.0".to_string()), Some("release/1.3".to_string()))
+        );
+        assert_eq!(
+            parse_range("v1.2.0..").unwrap(),
+            (Some("v1.2.0".to_string()), None)
+        );
+        assert_eq!(
+            parse_range("..main").unwrap(),
+            (None, Some("main".to_string()))
+        );
+        assert!(parse_range("v1.2.0").is_err());
+        assert!(parse_range("a...b").is_err());
+    }
+
+    #[test]
+    fn test_resolve_and_latest_tag() {
+        let dir = init_repo();
+        let first = commit(dir.path(), "first");
+        git(dir.path(), &["tag", "v1.0.0"]);
+        let second = commit(dir.path(), "second");
+        git(dir.path(), &["tag", "-a", "v1.1.0", "-m", "annotated"]);
+        let third = commit(dir.path(), "third");
+
+        let repo = gix::open(dir.path()).unwrap();
+        assert_eq!(resolve_commit(&repo, "v1.0.0").unwrap(), first);
+        // Annotated tags are peeled to their commit
+        assert_eq!(resolve_commit(&repo, "v1.1.0").unwrap(), second);
+        assert_eq!(resolve_commit(&repo, "HEAD").unwrap(), third);
+        assert!(resolve_commit(&repo, "does-not-exist").is_err());
+
+        assert_eq!(latest_tag(&repo, third).unwrap().as_deref(), Some("v1.1.0"));
+        assert_eq!(latest_tag(&repo, first).unwrap().as_deref(), Some("v1.0.0"));
+    }
+
+    /// Sorted output of `git rev-list`, to compare against
+    fn rev_list(dir: &std::path::Path, range: &str) -> Vec<ObjectId> {
+        let mut ids: Vec<ObjectId> = git(dir, &["rev-list", range])
+            .lines()
+            .map(|line| ObjectId::from_hex(line.as_bytes()).unwrap())
+            .collect();
+        ids.sort();
+        ids
+    }
+
+    fn sorted(mut ids: Vec<ObjectId>) -> Vec<ObjectId> {
+        ids.sort();
+        ids
+    }
+
+    #[test]
+    fn test_commits_in_range_linear() {
+        let dir = init_repo();
+        commit(dir.path(), "first");
+        let from = commit(dir.path(), "second");
+        let third = commit(dir.path(), "third");
+        let fourth = commit(dir.path(), "fourth");
+
+        let repo = gix::open(dir.path()).unwrap();
+        assert_eq!(
+            commits_in_range(&repo, Some(from), fourth).unwrap(),
+            vec![fourth, third]
+        );
+        assert!(commits_in_range(&repo, Some(fourth), fourth)
+            .unwrap()
+            .is_empty());
+        assert_eq!(commits_in_range(&repo, None, fourth).unwrap().len(), 4);
+    }
+
+    #[test]
+    fn test_commits_in_range_feature_branch_started_before_release() {
+        // main:    A---T---B---M
+        //           \         /
+        // feature:  F1---F2---
+        // F1 predates the release tag T but was only merged afterwards
+        let dir = init_repo();
+        let p = dir.path();
+        commit(p, "A");
+        git(p, &["checkout", "-q", "-b", "feature"]);
+        let f1 = commit(p, "F1");
+        git(p, &["checkout", "-q", "main"]);
+        let tag = commit(p, "T");
+        git(p, &["checkout", "-q", "feature"]);
+        let f2 = commit(p, "F2");
+        git(p, &["checkout", "-q", "main"]);
+        let b = commit(p, "B");
+        git(
+            p,
+            &["merge", "-q", "--no-ff", "-m", "Merge feature", "feature"],
+        );
+        let merge = resolve_commit(&gix::open(p).unwrap(), "HEAD").unwrap();
+
+        let repo = gix::open(p).unwrap();
+        let commits = commits_in_range(&repo, Some(tag), merge).unwrap();
+        assert_eq!(
+            sorted(commits.clone()),
+            rev_list(p, &format!("{}..{}", tag, merge))
+        );
+        assert_eq!(sorted(commits), sorted(vec![merge, b, f2, f1]));
+    }
+
+    #[test]
+    fn test_commits_in_range_excludes_released_commits_via_other_parent() {
+        // A long-lived branch that regularly merges main back in: commits that
+        // were part of the previous release must not show up again
+        let dir = init_repo();
+        let p = dir.path();
+        commit(p, "A");
+        git(p, &["checkout", "-q", "-b", "develop"]);
+        let d1 = commit(p, "D1");
+        git(p, &["checkout", "-q", "main"]);
+        let released = commit(p, "hotfix");
+        git(p, &["tag", "v1.0.0"]);
+        git(p, &["checkout", "-q", "develop"]);
+        git(
+            p,
+            &[
+                "merge",
+                "-q",
+                "--no-ff",
+                "-m",
+                "Merge main into develop",
+                "main",
+            ],
+        );
+        let back_merge = resolve_commit(&gix::open(p).unwrap(), "HEAD").unwrap();
+        let d2 = commit(p, "D2");
+        git(p, &["checkout", "-q", "main"]);
+        let c = commit(p, "C");
+        git(
+            p,
+            &["merge", "-q", "--no-ff", "-m", "Merge develop", "develop"],
+        );
+        let head = resolve_commit(&gix::open(p).unwrap(), "HEAD").unwrap();
+
+        let repo = gix::open(p).unwrap();
+        let from = resolve_commit(&repo, "v1.0.0").unwrap();
+        let commits = commits_in_range(&repo, Some(from), head).unwrap();
+        assert_eq!(sorted(commits.clone()), rev_list(p, "v1.0.0..HEAD"));
+        assert_eq!(
+            sorted(commits.clone()),
+            sorted(vec![head, c, d2, back_merge, d1])
+        );
+        assert!(!commits.contains(&released));
+    }
+
+    #[test]
+    fn test_commits_in_range_newest_first() {
+        let dir = init_repo();
+        let p = dir.path();
+        let from = commit(p, "A");
+        let mut expected: Vec<ObjectId> = [
+            "2024-01-01T10:00:00",
+            "2024-01-02T10:00:00",
+            "2024-01-03T10:00:00",
+        ]
+        .iter()
+        .map(|date| commit_at(p, "dated", date))
+        .collect();
+        expected.reverse();
+
+        let repo = gix::open(p).unwrap();
+        assert_eq!(
+            commits_in_range(&repo, Some(from), expected[0]).unwrap(),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_previous_tag_of_tagged_end() {
+        let dir = init_repo();
+        commit(dir.path(), "first");
+        git(dir.path(), &["tag", "v1.0.0"]);
+        commit(dir.path(), "second");
+        let head = commit(dir.path(), "third");
+        git(dir.path(), &["tag", "v1.1.0"]);
+
+        let repo = gix::open(dir.path()).unwrap();
+        // Releasing v1.1.0, which is already tagged on HEAD
+        let start = previous_tag(&repo, head, true).unwrap();
+        assert_eq!(start.as_deref(), Some("v1.0.0"));
+        let from = resolve_commit(&repo, "v1.0.0").unwrap();
+        assert_eq!(commits_in_range(&repo, Some(from), head).unwrap().len(), 2);
+        // Without an explicit end, the tag on HEAD is the start of the next release
+        assert_eq!(
+            previous_tag(&repo, head, false).unwrap().as_deref(),
+            Some("v1.1.0")
+        );
+    }
+
+    #[test]
+    fn test_latest_tag_none() {
+        let dir = init_repo();
+        let only = commit(dir.path(), "only");
+        let repo = gix::open(dir.path()).unwrap();
+        assert_eq!(latest_tag(&repo, only).unwrap(), None);
+    }
+}
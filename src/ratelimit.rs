@@ -0,0 +1,274 @@
+use anyhow::Result;
+use http::HeaderMap;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::time::Instant;
+
+use crate::warnings::{ForgeError, ProblemKind};
+
+/// How long to keep retrying requests that hit a rate limit
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    /// First wait when the response doesn't say how long to wait, doubled on every retry
+    pub initial_backoff: Duration,
+    /// Longest acceptable wait; giving up is better than hanging until an hourly limit resets
+    pub max_wait: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        // GitHub asks to wait at least a minute after a secondary rate limit without Retry-After
+        RetryPolicy {
+            max_retries: 5,
+            initial_backoff: Duration::from_secs(60),
+            max_wait: Duration::from_secs(5 * 60),
+        }
+    }
+}
+
+/// Rate limit state shared by all requests to one API, so concurrent requests back off together
+///
+/// Understands GitHub's `X-RateLimit-*` and GitLab's `RateLimit-*` headers as well as
+/// `Retry-After`, and treats 403 responses mentioning a rate limit as secondary rate limits.
+#[derive(Debug, Default)]
+pub struct RateLimiter {
+    policy: RetryPolicy,
+    paused_until: Mutex<Option<Instant>>,
+}
+
+fn header_u64(headers: &HeaderMap, names: &[&str]) -> Option<u64> {
+    names.iter().find_map(|name| {
+        headers
+            .get(*name)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.trim().parse().ok())
+    })
+}
+
+impl RateLimiter {
+    pub fn new(policy: RetryPolicy) -> Self {
+        RateLimiter {
+            policy,
+            paused_until: Mutex::new(None),
+        }
+    }
+
+    /// Sleep while an earlier response said the limit is used up
+    pub async fn wait(&self) {
+        let until = *self.paused_until.lock().unwrap();
+        if let Some(until) = until {
+            tokio::time::sleep_until(until).await;
+        }
+    }
+
+    fn pause_for(&self, delay: Duration) {
+        let until = Instant::now() + delay;
+        let mut paused_until = self.paused_until.lock().unwrap();
+        // Spelled out, `Option::is_none_or` needs Rust 1.82
+        let extends_pause = match *paused_until {
+            Some(current) => current < until,
+            None => true,
+        };
+        if extends_pause {
+            *paused_until = Some(until);
+        }
+    }
+
+    /// Look at a response: `None` to use it, or how long to wait before retrying the request
+    ///
+    /// `attempt` counts the retries so far. Fails when out of retries or when the limit
+    /// resets too far in the future.
+    pub fn check(
+        &self,
+        status: u16,
+        headers: &HeaderMap,
+        body: &str,
+        attempt: u32,
+    ) -> Result<Option<Duration>> {
+        let remaining = header_u64(headers, &["x-ratelimit-remaining", "ratelimit-remaining"]);
+        let reset_in =
+            header_u64(headers, &["x-ratelimit-reset", "ratelimit-reset"]).map(|reset| {
+                let now = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_secs();
+                // A second of slack for clock differences
+                Duration::from_secs(reset.saturating_sub(now) + 1)
+            });
+        let retry_after = header_u64(headers, &["retry-after"]).map(Duration::from_secs);
+
+        let limited = status == 429
+            || (status == 403
+                && (retry_after.is_some()
+                    || remaining == Some(0)
+                    || body.to_lowercase().contains("rate limit")))
+            || (status == 200 && is_graphql_rate_limited(body));
+
+        if !limited {
+            // The last request allowed; hold off the following ones until the reset
+            if remaining == Some(0) {
+                if let Some(reset_in) = reset_in.filter(|d| *d <= self.policy.max_wait) {
+                    self.pause_for(reset_in);
+                }
+            }
+            return Ok(None);
+        }
+
+        let delay = retry_after
+            .or(reset_in.filter(|_| remaining == Some(0)))
+            .unwrap_or_else(|| self.policy.initial_backoff * 2u32.saturating_pow(attempt));
+        if attempt >= self.policy.max_retries {
+            return Err(ForgeError::new(
+                ProblemKind::RateLimited,
+                format!(
+                    "Rate limited (HTTP {}), giving up after {} retries",
+                    status, attempt
+                ),
+            )
+            .into());
+        }
+        if delay > self.policy.max_wait {
+            return Err(ForgeError::new(
+                ProblemKind::RateLimited,
+                format!(
+                    "Rate limited (HTTP {}) for another {} seconds",
+                    status,
+                    delay.as_secs()
+                ),
+            )
+            .into());
+        }
+        self.pause_for(delay);
+        Ok(Some(delay))
+    }
+}
+
+/// GitHub's GraphQL API reports an exceeded limit with HTTP 200 and an error of type
+/// `RATE_LIMITED` instead of a 403
+fn is_graphql_rate_limited(body: &str) -> bool {
+    // Cheap check first, most bodies are no errors at all
+    if !body.contains("RATE_LIMITED") {
+        return false;
+    }
+    serde_json::from_str::<serde_json::Value>(body)
+        .ok()
+        .and_then(|response| response["errors"].as_array().cloned())
+        .is_some_and(|errors| errors.iter().any(|e| e["type"] == "RATE_LIMITED"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(pairs: &[(&'static str, String)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in pairs {
+            headers.insert(*name, value.parse().unwrap());
+        }
+        headers
+    }
+
+    fn epoch_in(seconds: u64) -> String {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+        (now.as_secs() + seconds).to_string()
+    }
+
+    fn limiter() -> RateLimiter {
+        RateLimiter::new(RetryPolicy {
+            max_retries: 3,
+            initial_backoff: Duration::from_secs(2),
+            max_wait: Duration::from_secs(120),
+        })
+    }
+
+    #[test]
+    fn test_success_is_used() {
+        let limiter = limiter();
+        let ok = headers(&[("x-ratelimit-remaining", "4999".to_string())]);
+        assert_eq!(limiter.check(200, &ok, "", 0).unwrap(), None);
+        assert!(limiter.paused_until.lock().unwrap().is_none());
+        // A 403 that isn't about rate limits is an ordinary error for the caller
+        assert_eq!(
+            limiter
+                .check(403, &HeaderMap::new(), "Resource not accessible", 0)
+                .unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_retry_after() {
+        let limiter = limiter();
+        let h = headers(&[("retry-after", "30".to_string())]);
+        assert_eq!(
+            limiter.check(429, &h, "", 0).unwrap(),
+            Some(Duration::from_secs(30))
+        );
+        assert!(limiter.paused_until.lock().unwrap().is_some());
+    }
+
+    #[test]
+    fn test_primary_limit_waits_for_reset() {
+        let limiter = limiter();
+        let h = headers(&[
+            ("x-ratelimit-remaining", "0".to_string()),
+            ("x-ratelimit-reset", epoch_in(10)),
+        ]);
+        let delay = limiter
+            .check(403, &h, "API rate limit exceeded", 0)
+            .unwrap();
+        assert!(delay.is_some_and(|d| d >= Duration::from_secs(10) && d <= Duration::from_secs(12)));
+
+        // An hourly limit that resets much later isn't waited for
+        let h = headers(&[
+            ("ratelimit-remaining", "0".to_string()),
+            ("ratelimit-reset", epoch_in(3000)),
+        ]);
+        assert!(limiter.check(429, &h, "", 0).is_err());
+    }
+
+    #[test]
+    fn test_secondary_limit_backs_off_exponentially() {
+        let limiter = limiter();
+        let body = "You have exceeded a secondary rate limit";
+        let none = HeaderMap::new();
+        assert_eq!(
+            limiter.check(403, &none, body, 0).unwrap(),
+            Some(Duration::from_secs(2))
+        );
+        assert_eq!(
+            limiter.check(403, &none, body, 2).unwrap(),
+            Some(Duration::from_secs(8))
+        );
+        assert!(limiter.check(403, &none, body, 3).is_err());
+    }
+
+    #[test]
+    fn test_graphql_rate_limited() {
+        let limiter = limiter();
+        let body = r#"{"data":null,"errors":[{"type":"RATE_LIMITED","message":"API rate limit exceeded"}]}"#;
+        assert_eq!(
+            limiter.check(200, &HeaderMap::new(), body, 0).unwrap(),
+            Some(Duration::from_secs(2))
+        );
+        // Other GraphQL errors are for the caller
+        let body = r#"{"data":null,"errors":[{"type":"NOT_FOUND","message":"RATE_LIMITED repo"}]}"#;
+        assert_eq!(
+            limiter.check(200, &HeaderMap::new(), body, 0).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_exhausted_limit_pauses_following_requests() {
+        let limiter = limiter();
+        let h = headers(&[
+            ("x-ratelimit-remaining", "0".to_string()),
+            ("x-ratelimit-reset", epoch_in(30)),
+        ]);
+        assert_eq!(limiter.check(200, &h, "", 0).unwrap(), None);
+        let until = limiter.paused_until.lock().unwrap().unwrap();
+        assert!(until > Instant::now() + Duration::from_secs(29));
+    }
+}
@@ -0,0 +1,154 @@
+//! Release-note text written by the author of a change, instead of its commit subject
+//!
+//! PR descriptions can carry it in a ```` ```release-note ```` block or a `## Release notes`
+//! section, commit messages in a `Release-Note:` or `Changelog:` trailer. `NONE` says the
+//! change doesn't belong in the notes at all.
+
+use regex::Regex;
+
+/// What the author wants the notes to say about a change
+#[derive(Debug, Clone, PartialEq)]
+pub enum ReleaseNote {
+    /// Leave the change out
+    Skip,
+    Text(String),
+}
+
+/// Values of GitLab's `Changelog:` trailer, which name a category rather than give a text
+const GITLAB_CHANGELOG_CATEGORIES: [&str; 8] = [
+    "added",
+    "fixed",
+    "changed",
+    "deprecated",
+    "removed",
+    "security",
+    "performance",
+    "other",
+];
+
+impl ReleaseNote {
+    /// Note from a PR description: a `release-note` block, else a "Release notes" section
+    pub fn from_description(description: &str) -> Option<ReleaseNote> {
+        let description = Regex::new(r"(?s)<!--.*?-->")
+            .unwrap()
+            .replace_all(description, "")
+            .replace("\r\n", "\n");
+        let block = Regex::new(r"(?ms)^\s*```+\s*release-notes?\s*$\n(.*?)^\s*```")
+            .unwrap()
+            .captures(&description)
+            .map(|caps| caps[1].to_string());
+        block
+            .or_else(|| section(&description))
+            .and_then(|text| ReleaseNote::from_text(&text))
+    }
+
+    /// Note from a `Release-Note:` or `Changelog:` trailer at the end of a commit message body
+    pub fn from_trailers(body: &str) -> Option<ReleaseNote> {
+        let trailers = body.trim_end().rsplit("\n\n").next()?;
+        let re_trailer = Regex::new(r"(?i)^(release-notes?|changelog):\s*(.*)$").unwrap();
+        trailers.lines().find_map(|line| {
+            let caps = re_trailer.captures(line.trim())?;
+            let value = caps[2].trim();
+            if caps[1].eq_ignore_ascii_case("changelog")
+                && GITLAB_CHANGELOG_CATEGORIES.contains(&value.to_lowercase().as_str())
+            {
+                return None;
+            }
+            ReleaseNote::from_text(value)
+        })
+    }
+
+    /// One line of text; `None` when there is none, e.g. an unfilled PR template
+    fn from_text(text: &str) -> Option<ReleaseNote> {
+        let lines: Vec<&str> = text
+            .lines()
+            .map(|line| line.trim().trim_start_matches(['-', '*']).trim())
+            .filter(|line| !line.is_empty())
+            .collect();
+        let text = lines.join(" ");
+        if text.is_empty() {
+            return None;
+        }
+        if text.trim_end_matches('.').eq_ignore_ascii_case("none") {
+            return Some(ReleaseNote::Skip);
+        }
+        Some(ReleaseNote::Text(text))
+    }
+}
+
+/// Content below a "Release notes" heading of any level, up to the next heading
+fn section(description: &str) -> Option<String> {
+    let re_heading = Regex::new(r"^#{1,6}\s+(.*?)\s*#*\s*$").unwrap();
+    let mut content: Option<Vec<&str>> = None;
+    for line in description.lines() {
+        if let Some(caps) = re_heading.captures(line) {
+            if content.is_some() {
+                break;
+            }
+            let title = caps[1].trim_end_matches(':').to_lowercase();
+            if title == "release notes" || title == "release note" {
+                content = Some(Vec::new());
+            }
+        } else if let Some(content) = &mut content {
+            content.push(line);
+        }
+    }
+    content.map(|lines| lines.join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(s: &str) -> Option<ReleaseNote> {
+        Some(ReleaseNote::Text(s.to_string()))
+    }
+
+    #[test]
+    fn test_release_note_block() {
+        let description = "Refactors the exporter.\r\n\r\n```release-note\r\nCSV exports now \
+                           include a header row.\r\n```\r\n\r\n## Release notes\r\nIgnored\r\n";
+        assert_eq!(
+            ReleaseNote::from_description(description),
+            text("CSV exports now include a header row.")
+        );
+        assert_eq!(
+            ReleaseNote::from_description("```release-note\nNONE\n```"),
+            Some(ReleaseNote::Skip)
+        );
+    }
+
+    #[test]
+    fn test_release_notes_section() {
+        let description = "## Summary\nInternal details\n\n## Release notes\n\
+                           <!-- Describe the change for users, or write NONE -->\n\
+                           - Sessions are refreshed\n  before they expire\n\n## Testing\n\
+                           Unit tests";
+        assert_eq!(
+            ReleaseNote::from_description(description),
+            text("Sessions are refreshed before they expire")
+        );
+
+        // An unfilled template gives no note
+        let template = "## Release notes\n<!-- Describe the change for users, or write NONE -->\n\n## Testing\n";
+        assert_eq!(ReleaseNote::from_description(template), None);
+        assert_eq!(ReleaseNote::from_description("Just a description"), None);
+    }
+
+    #[test]
+    fn test_trailers() {
+        let body = "Longer explanation.\n\nRelease-Note: Adds dark mode\nSigned-off-by: Dev <dev@example.com>";
+        assert_eq!(ReleaseNote::from_trailers(body), text("Adds dark mode"));
+        assert_eq!(
+            ReleaseNote::from_trailers("changelog: none"),
+            Some(ReleaseNote::Skip)
+        );
+        // GitLab's trailer names a category
+        assert_eq!(ReleaseNote::from_trailers("Changelog: fixed"), None);
+        // Only the last paragraph holds trailers
+        assert_eq!(
+            ReleaseNote::from_trailers("Release-Note: old\n\nSigned-off-by: Dev"),
+            None
+        );
+    }
+}
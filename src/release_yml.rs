@@ -0,0 +1,247 @@
+//! GitHub's configuration for automatically generated release notes, `.github/release.yml`
+//!
+//! Its categories and exclusions are applied on top of `.releasenotes.toml`, so the notes
+//! come out grouped the way GitHub's "Generate release notes" button groups them.
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+
+use crate::config::{CategoryRule, Config};
+
+/// Where GitHub looks for the file, in this order
+pub const RELEASE_YML_PATHS: [&str; 2] = [".github/release.yml", ".github/release.yaml"];
+
+#[derive(Debug, Clone, Default, Deserialize, PartialEq)]
+pub struct ReleaseYml {
+    #[serde(default)]
+    pub changelog: Changelog,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, PartialEq)]
+pub struct Changelog {
+    #[serde(default)]
+    pub exclude: Exclude,
+    #[serde(default)]
+    pub categories: Vec<Category>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, PartialEq)]
+pub struct Exclude {
+    #[serde(default)]
+    pub labels: Vec<String>,
+    #[serde(default)]
+    pub authors: Vec<String>,
+}
+
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+pub struct Category {
+    pub title: String,
+    /// `*` collects everything no earlier category matched
+    #[serde(default)]
+    pub labels: Vec<String>,
+    #[serde(default)]
+    pub exclude: Exclude,
+}
+
+impl ReleaseYml {
+    pub fn parse(content: &str) -> Result<ReleaseYml> {
+        // An empty file is valid and configures nothing
+        if content.trim().is_empty() {
+            return Ok(ReleaseYml::default());
+        }
+        Ok(serde_norway::from_str(content)?)
+    }
+
+    /// Read the file from the tree of `commit`, `None` when it has none
+    pub fn from_commit(commit: &gix::Commit<'_>) -> Result<Option<ReleaseYml>> {
+        let tree = commit.tree()?;
+        for path in RELEASE_YML_PATHS {
+            if let Some(entry) = tree.lookup_entry_by_path(path)? {
+                let blob = entry.object()?;
+                let content = String::from_utf8_lossy(&blob.data);
+                return ReleaseYml::parse(&content)
+                    .map(Some)
+                    .with_context(|| format!("Invalid {}", path));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Add the exclusions to `config`, and use the categories unless it defines its own
+    pub fn apply(&self, config: &mut Config) {
+        let exclude = &self.changelog.exclude;
+        config.skip_labels.extend(exclude.labels.iter().cloned());
+        config.skip_authors.extend(exclude.authors.iter().cloned());
+
+        // The built-in categories go by commit type, which GitHub knows nothing about
+        if self.changelog.categories.is_empty() || config.categories != Config::default().categories
+        {
+            return;
+        }
+        let mut keys: Vec<String> = Vec::new();
+        config.categories = self
+            .changelog
+            .categories
+            .iter()
+            .map(|category| {
+                let key = unique_key(&category.title, &keys);
+                keys.push(key.clone());
+                CategoryRule {
+                    key,
+                    heading: category.title.clone(),
+                    types: Vec::new(),
+                    subject: None,
+                    body: None,
+                    author: None,
+                    path: None,
+                    labels: category.labels.clone(),
+                    exclude_labels: category.exclude.labels.clone(),
+                    exclude_authors: category.exclude.authors.clone(),
+                }
+            })
+            .collect();
+    }
+}
+
+/// Category key made from a title, e.g. `exciting-new-features` for "Exciting New Features 🎉"
+fn unique_key(title: &str, taken: &[String]) -> String {
+    let words: Vec<String> = title
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect();
+    let base = if words.is_empty() {
+        "category".to_string()
+    } else {
+        words.join("-")
+    };
+    let mut key = base.clone();
+    let mut n = 2;
+    while taken.contains(&key) {
+        key = format!("{}-{}", base, n);
+        n += 1;
+    }
+    key
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::CommitFacts;
+    use crate::test_support::{commit, git, init_repo};
+
+    const RELEASE_YML: &str = r#"
+# .github/release.yml
+changelog:
+  exclude:
+    labels:
+      - ignore-for-release
+    authors:
+      - octocat
+  categories:
+    - title: Breaking Changes 🛠
+      labels:
+        - Semver-Major
+        - breaking-change
+    - title: Exciting New Features 🎉
+      labels: [Semver-Minor, enhancement]
+      exclude:
+        labels: [wip]
+    - title: Other Changes
+      labels:
+        - "*"
+"#;
+
+    #[test]
+    fn test_apply_release_yml() {
+        let release_yml = ReleaseYml::parse(RELEASE_YML).unwrap();
+        let mut config = Config::default();
+        release_yml.apply(&mut config);
+
+        let keys: Vec<&str> = config.categories.iter().map(|c| c.key.as_str()).collect();
+        assert_eq!(
+            keys,
+            vec!["breaking-changes", "exciting-new-features", "other-changes"]
+        );
+        assert_eq!(config.categories[1].heading, "Exciting New Features 🎉");
+
+        let labels =
+            |names: &[&str]| -> Vec<String> { names.iter().map(|n| n.to_string()).collect() };
+        let enhancement = labels(&["enhancement"]);
+        let wip = labels(&["enhancement", "wip"]);
+        let ignored = labels(&["ignore-for-release"]);
+        let commit = CommitFacts {
+            subject: "Add export",
+            author: "Jane Doe",
+            ..Default::default()
+        };
+        let category = |labels: &[String]| {
+            config
+                .categorize(&CommitFacts { labels, ..commit })
+                .map(str::to_string)
+        };
+        assert_eq!(
+            category(&enhancement).as_deref(),
+            Some("exciting-new-features")
+        );
+        assert_eq!(category(&wip).as_deref(), Some("other-changes"));
+        assert_eq!(category(&[]).as_deref(), Some("other-changes"));
+
+        assert!(config.is_excluded(&CommitFacts {
+            labels: &ignored,
+            ..commit
+        }));
+        assert!(config.is_excluded(&CommitFacts {
+            login: Some("octocat"),
+            ..commit
+        }));
+        assert!(!config.is_excluded(&commit));
+    }
+
+    #[test]
+    fn test_own_categories_are_kept() {
+        let mut config = Config::parse(
+            r#"
+            [[categories]]
+            key = "docs"
+            heading = "Documentation"
+            path = "^docs/"
+            "#,
+        )
+        .unwrap();
+        ReleaseYml::parse(RELEASE_YML).unwrap().apply(&mut config);
+        assert_eq!(config.categories.len(), 1);
+        assert!(config
+            .skip_labels
+            .contains(&"ignore-for-release".to_string()));
+
+        assert_eq!(ReleaseYml::parse("").unwrap(), ReleaseYml::default());
+        assert!(ReleaseYml::parse("changelog: [").is_err());
+    }
+
+    #[test]
+    fn test_unique_keys() {
+        let taken = vec!["other".to_string()];
+        assert_eq!(unique_key("🚀 Other", &taken), "other-2");
+        assert_eq!(unique_key("🎉", &taken), "category");
+    }
+
+    #[test]
+    fn test_read_from_commit() {
+        let dir = init_repo();
+        commit(dir.path(), "Initial commit");
+        let repo = gix::open(dir.path()).unwrap();
+        let head = repo.head_commit().unwrap();
+        assert_eq!(ReleaseYml::from_commit(&head).unwrap(), None);
+
+        std::fs::create_dir(dir.path().join(".github")).unwrap();
+        std::fs::write(dir.path().join(".github/release.yml"), RELEASE_YML).unwrap();
+        git(dir.path(), &["add", "."]);
+        commit(dir.path(), "Add release.yml");
+        let repo = gix::open(dir.path()).unwrap();
+        let head = repo.head_commit().unwrap();
+        let release_yml = ReleaseYml::from_commit(&head).unwrap().unwrap();
+        assert_eq!(release_yml.changelog.categories.len(), 3);
+        assert_eq!(release_yml.changelog.exclude.authors, vec!["octocat"]);
+    }
+}
@@ -0,0 +1,70 @@
+use gix::Repository;
+
+/// Remote used when neither `--remote` nor the config file name one
+pub const DEFAULT_REMOTE: &str = "origin";
+/// Branch used when it can't be detected from the remote
+pub const DEFAULT_BRANCH: &str = "main";
+
+/// The remote's default branch, read from `refs/remotes/<remote>/HEAD`
+///
+/// This symbolic ref is set up by `git clone` (or `git remote set-head <remote> --auto`).
+pub fn default_branch(repo: &Repository, remote: &str) -> Option<String> {
+    let head_ref = repo
+        .find_reference(format!("refs/remotes/{}/HEAD", remote).as_str())
+        .ok()?;
+    let target = head_ref.target().try_name()?.as_bstr().to_string();
+    target
+        .strip_prefix(&format!("refs/remotes/{}/", remote))
+        .map(|branch| branch.to_string())
+}
+
+/// Fetch URL of the given remote
+pub fn remote_url(repo: &Repository, remote: &str) -> Option<String> {
+    repo.find_remote(remote).ok().and_then(|r| {
+        r.url(gix::remote::Direction::Fetch)
+            .map(|u| u.to_bstring().to_string())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{commit, git, init_repo};
+
+    #[test]
+    fn test_default_branch_from_clone() {
+        let upstream = init_repo();
+        git(upstream.path(), &["checkout", "-q", "-b", "trunk"]);
+        commit(upstream.path(), "first");
+
+        let clone = tempfile::tempdir().unwrap();
+        git(
+            clone.path(),
+            &[
+                "clone",
+                "-q",
+                "--origin",
+                "upstream",
+                upstream.path().to_str().unwrap(),
+                ".",
+            ],
+        );
+
+        let repo = gix::open(clone.path()).unwrap();
+        assert_eq!(default_branch(&repo, "upstream").as_deref(), Some("trunk"));
+        assert_eq!(default_branch(&repo, "origin"), None);
+        assert_eq!(
+            remote_url(&repo, "upstream").as_deref(),
+            upstream.path().to_str()
+        );
+        assert_eq!(remote_url(&repo, "origin"), None);
+    }
+
+    #[test]
+    fn test_default_branch_without_remote() {
+        let dir = init_repo();
+        commit(dir.path(), "first");
+        let repo = gix::open(dir.path()).unwrap();
+        assert_eq!(default_branch(&repo, DEFAULT_REMOTE), None);
+    }
+}
@@ -0,0 +1,149 @@
+//! Helpers for tests that need a real git repository or a stand-in for the GitHub API
+
+use gix::ObjectId;
+use serde_json::{json, Value};
+use std::path::Path;
+use std::process::Command;
+
+pub(crate) fn git(dir: &Path, args: &[&str]) -> String {
+    git_with_env(dir, args, &[])
+}
+
+fn git_with_env(dir: &Path, args: &[&str], env: &[(&str, &str)]) -> String {
+    let output = Command::new("git")
+        .args(args)
+        .envs(env.iter().copied())
+        .current_dir(dir)
+        .env("GIT_AUTHOR_NAME", "Dev")
+        .env("GIT_AUTHOR_EMAIL", "dev@example.com")
+        .env("GIT_COMMITTER_NAME", "Dev")
+        .env("GIT_COMMITTER_EMAIL", "dev@example.com")
+        .env("GIT_CONFIG_GLOBAL", "/dev/null")
+        .env("GIT_CONFIG_NOSYSTEM", "1")
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "git {:?} failed: {}",
+        args,
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8_lossy(&output.stdout).trim().to_string()
+}
+
+pub(crate) fn commit(dir: &Path, message: &str) -> ObjectId {
+    git(dir, &["commit", "--allow-empty", "-q", "-m", message]);
+    ObjectId::from_hex(git(dir, &["rev-parse", "HEAD"]).as_bytes()).unwrap()
+}
+
+/// Commit with both author and committer date set, for tests that depend on ordering
+pub(crate) fn commit_at(dir: &Path, message: &str, date: &str) -> ObjectId {
+    git_with_env(
+        dir,
+        &["commit", "--allow-empty", "-q", "-m", message],
+        &[("GIT_AUTHOR_DATE", date), ("GIT_COMMITTER_DATE", date)],
+    );
+    ObjectId::from_hex(git(dir, &["rev-parse", "HEAD"]).as_bytes()).unwrap()
+}
+
+pub(crate) fn init_repo() -> tempfile::TempDir {
+    let dir = tempfile::tempdir().unwrap();
+    git(dir.path(), &["init", "-q", "-b", "main"]);
+    dir
+}
+
+/// A GitHub user as embedded in API responses
+pub(crate) fn github_user_json(login: &str) -> Value {
+    let url = format!("https://api.github.com/users/{}", login);
+    json!({
+        "login": login,
+        "id": 1,
+        "node_id": "U_node",
+        "avatar_url": "https://avatars.githubusercontent.com/u/1",
+        "gravatar_id": "",
+        "url": url,
+        "html_url": format!("https://github.com/{}", login),
+        "followers_url": format!("{}/followers", url),
+        "following_url": format!("{}/following", url),
+        "gists_url": format!("{}/gists", url),
+        "starred_url": format!("{}/starred", url),
+        "subscriptions_url": format!("{}/subscriptions", url),
+        "organizations_url": format!("{}/orgs", url),
+        "repos_url": format!("{}/repos", url),
+        "events_url": format!("{}/events", url),
+        "received_events_url": format!("{}/received_events", url),
+        "type": "User",
+        "site_admin": false
+    })
+}
+
+/// An item of the issue search results, a pull request unless `is_pr` is false
+pub(crate) fn github_issue_json(number: u64, is_pr: bool) -> Value {
+    let url = format!("https://api.github.com/repos/owner/repo/issues/{}", number);
+    let mut issue = json!({
+        "id": number,
+        "node_id": "I_node",
+        "url": url,
+        "repository_url": "https://api.github.com/repos/owner/repo",
+        "labels_url": format!("{}/labels", url),
+        "comments_url": format!("{}/comments", url),
+        "events_url": format!("{}/events", url),
+        "html_url": format!("https://github.com/owner/repo/issues/{}", number),
+        "number": number,
+        "state": "closed",
+        "title": format!("Issue {}", number),
+        "user": github_user_json("octocat"),
+        "labels": [],
+        "assignees": [],
+        "locked": false,
+        "comments": 0,
+        "created_at": "2024-01-01T00:00:00Z",
+        "updated_at": "2024-01-01T00:00:00Z"
+    });
+    if is_pr {
+        issue["pull_request"] = json!({
+            "url": format!("https://api.github.com/repos/owner/repo/pulls/{}", number),
+            "html_url": format!("https://github.com/owner/repo/pull/{}", number),
+            "diff_url": format!("https://github.com/owner/repo/pull/{}.diff", number),
+            "patch_url": format!("https://github.com/owner/repo/pull/{}.patch", number)
+        });
+    }
+    issue
+}
+
+/// A pull request as returned by `GET /repos/owner/repo/pulls/{number}`
+pub(crate) fn github_pull_json(
+    number: u64,
+    title: &str,
+    body: &str,
+    labels: &[&str],
+    login: &str,
+) -> Value {
+    let labels: Vec<Value> = labels
+        .iter()
+        .enumerate()
+        .map(|(i, name)| {
+            json!({
+                "id": i,
+                "node_id": "L_node",
+                "url": format!("https://api.github.com/repos/owner/repo/labels/{}", name),
+                "name": name,
+                "color": "ededed",
+                "default": false
+            })
+        })
+        .collect();
+    json!({
+        "url": format!("https://api.github.com/repos/owner/repo/pulls/{}", number),
+        "id": number,
+        "number": number,
+        "title": title,
+        "body": body,
+        "labels": labels,
+        "user": github_user_json(login),
+        "locked": false,
+        "maintainer_can_modify": false,
+        "head": { "ref": "feature", "sha": "abc" },
+        "base": { "ref": "main", "sha": "def" }
+    })
+}
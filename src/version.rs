@@ -0,0 +1,260 @@
+//! Version numbers of dependencies, compared the way package managers do
+//!
+//! Besides semver this understands `v` prefixes, requirement operators like `^1.2`, Maven
+//! versions like `5.3.1.RELEASE` or `31.1-jre`, and calendar versions like `2024.01.15`.
+
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::fmt;
+
+/// Qualifiers that mark a release rather than a pre-release, e.g. in `5.3.1.RELEASE`
+const RELEASE_QUALIFIERS: [&str; 5] = ["release", "final", "ga", "jre", "android"];
+
+/// How big a version change is, in order of how much attention it needs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum UpdateKind {
+    Patch,
+    Minor,
+    /// May break things: the leftmost non-zero component changed, as Cargo sees it
+    Major,
+    /// Goes to a pre-release, which isn't meant to be depended on
+    PreRelease,
+}
+
+impl UpdateKind {
+    /// How big the change between two versions is, `None` when they can't be compared or are
+    /// the same version
+    ///
+    /// Follows Cargo's rules for 0.x versions: `0.9 → 0.10` and `0.0.3 → 0.0.4` are major.
+    /// Downgrades are classified like the upgrade they undo.
+    pub fn classify(from: &str, to: &str) -> Option<UpdateKind> {
+        let (from, to) = (Version::parse(from)?, Version::parse(to)?);
+        if from == to {
+            return None;
+        }
+        if to.is_pre_release() {
+            return Some(UpdateKind::PreRelease);
+        }
+        let (low, high) = if from <= to {
+            (&from, &to)
+        } else {
+            (&to, &from)
+        };
+        let len = low.components.len().max(high.components.len());
+        let Some(changed) = (0..len).find(|&i| low.component(i) != high.component(i)) else {
+            // Only the pre-release of `from` differs, e.g. 2.0.0-rc.1 → 2.0.0
+            return Some(UpdateKind::Patch);
+        };
+        let leftmost_non_zero = (0..len).find(|&i| low.component(i) != 0).unwrap_or(len);
+        Some(if changed <= leftmost_non_zero {
+            UpdateKind::Major
+        } else if changed == 1 {
+            UpdateKind::Minor
+        } else {
+            UpdateKind::Patch
+        })
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            UpdateKind::Patch => "patch",
+            UpdateKind::Minor => "minor",
+            UpdateKind::Major => "major",
+            UpdateKind::PreRelease => "pre-release",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Version {
+    /// Numeric components, `[1, 2, 3]` for `1.2.3`
+    pub components: Vec<u64>,
+    /// Pre-release identifiers, e.g. `["rc", "1"]` for `2.0.0-rc.1` or `2.0.0-RC1`
+    pub pre: Vec<String>,
+}
+
+impl Version {
+    /// `None` for anything not starting with a number after the prefix, e.g. a digest or `latest`
+    pub fn parse(version: &str) -> Option<Version> {
+        let version = version
+            .trim()
+            .trim_start_matches(['^', '~', '=', '>', '<', ' '])
+            .trim_start_matches(['v', 'V']);
+        // Build metadata doesn't take part in comparisons
+        let version = version.split('+').next().unwrap_or_default();
+
+        let mut components = Vec::new();
+        let mut rest = version;
+        loop {
+            let digits = rest.len() - rest.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+            if digits == 0 {
+                break;
+            }
+            components.push(rest[..digits].parse().ok()?);
+            rest = &rest[digits..];
+            match rest.strip_prefix('.') {
+                Some(after) if after.starts_with(|c: char| c.is_ascii_digit()) => rest = after,
+                _ => break,
+            }
+        }
+        if components.is_empty() {
+            return None;
+        }
+
+        let qualifier = rest.trim_start_matches(['-', '.', '_']).to_lowercase();
+        let pre = if RELEASE_QUALIFIERS.contains(&qualifier.as_str()) {
+            Vec::new()
+        } else {
+            identifiers(&qualifier)
+        };
+        Some(Version { components, pre })
+    }
+
+    pub fn is_pre_release(&self) -> bool {
+        !self.pre.is_empty()
+    }
+
+    /// Component `i`, with missing ones being 0 so `1.2` equals `1.2.0`
+    pub fn component(&self, i: usize) -> u64 {
+        self.components.get(i).copied().unwrap_or(0)
+    }
+}
+
+/// Pre-release identifiers, also split where letters and digits meet, so `rc10` sorts after `rc9`
+fn identifiers(qualifier: &str) -> Vec<String> {
+    let mut identifiers: Vec<String> = Vec::new();
+    for part in qualifier.split(['.', '-', '_']).filter(|p| !p.is_empty()) {
+        let mut current = String::new();
+        for c in part.chars() {
+            let boundary = current
+                .chars()
+                .last()
+                .is_some_and(|last| last.is_ascii_digit() != c.is_ascii_digit());
+            if boundary {
+                identifiers.push(std::mem::take(&mut current));
+            }
+            current.push(c);
+        }
+        identifiers.push(current);
+    }
+    identifiers
+}
+
+fn compare_identifiers(a: &str, b: &str) -> Ordering {
+    match (a.parse::<u64>(), b.parse::<u64>()) {
+        (Ok(a), Ok(b)) => a.cmp(&b),
+        // Numeric identifiers sort before alphanumeric ones, as in semver
+        (Ok(_), Err(_)) => Ordering::Less,
+        (Err(_), Ok(_)) => Ordering::Greater,
+        (Err(_), Err(_)) => a.cmp(b),
+    }
+}
+
+impl Ord for Version {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let len = self.components.len().max(other.components.len());
+        (0..len)
+            .map(|i| self.component(i).cmp(&other.component(i)))
+            .find(|ordering| ordering.is_ne())
+            .unwrap_or_else(|| match (self.is_pre_release(), other.is_pre_release()) {
+                // A pre-release comes before its release
+                (true, false) => Ordering::Less,
+                (false, true) => Ordering::Greater,
+                _ => self
+                    .pre
+                    .iter()
+                    .zip(&other.pre)
+                    .map(|(a, b)| compare_identifiers(a, b))
+                    .find(|ordering| ordering.is_ne())
+                    .unwrap_or_else(|| self.pre.len().cmp(&other.pre.len())),
+            })
+    }
+}
+
+impl PartialOrd for Version {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Version {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Version {}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let components: Vec<String> = self.components.iter().map(u64::to_string).collect();
+        write!(f, "{}", components.join("."))?;
+        if self.is_pre_release() {
+            write!(f, "-{}", self.pre.join("."))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn v(version: &str) -> Version {
+        Version::parse(version).unwrap()
+    }
+
+    #[test]
+    fn test_parse_versions() {
+        assert_eq!(v("v1.2.3").components, vec![1, 2, 3]);
+        assert_eq!(v("^0.9").components, vec![0, 9]);
+        assert_eq!(v("2024.01.15").components, vec![2024, 1, 15]);
+        assert_eq!(v("5.3.1.RELEASE").to_string(), "5.3.1");
+        assert_eq!(v("31.1-jre").to_string(), "31.1");
+        assert_eq!(v("2.0.0-RC10+build.5").to_string(), "2.0.0-rc.10");
+        assert!(v("3.0.0-M1").is_pre_release());
+        assert!(Version::parse("latest").is_none());
+        assert!(Version::parse("").is_none());
+    }
+
+    #[test]
+    fn test_compare_versions() {
+        assert!(v("0.10.0") > v("0.9.9"));
+        assert!(v("v2.0") > v("1.99.0"));
+        assert_eq!(v("1.2"), v("1.2.0"));
+        assert!(v("2.0.0-rc.1") < v("2.0.0"));
+        assert!(v("2.0.0-rc9") < v("2.0.0-rc10"));
+        assert!(v("2.0.0-alpha") < v("2.0.0-beta.2"));
+        assert!(v("5.3.1.RELEASE") < v("5.3.2"));
+        assert!(v("2023.12.1") < v("2024.1.0"));
+    }
+
+    #[test]
+    fn test_classify_updates() {
+        let kind = |from, to| UpdateKind::classify(from, to);
+        assert_eq!(kind("1.2.3", "1.2.4"), Some(UpdateKind::Patch));
+        assert_eq!(kind("1.2.3", "1.3.0"), Some(UpdateKind::Minor));
+        assert_eq!(kind("v1.9.0", "v2.0.0"), Some(UpdateKind::Major));
+        assert_eq!(kind("1.9", "2"), Some(UpdateKind::Major));
+        // 0.x versions, as Cargo reads them
+        assert_eq!(kind("0.9.3", "0.10.0"), Some(UpdateKind::Major));
+        assert_eq!(kind("0.9.3", "0.9.4"), Some(UpdateKind::Patch));
+        assert_eq!(kind("0.0.3", "0.0.4"), Some(UpdateKind::Major));
+        // Pre-releases, and leaving one for its release
+        assert_eq!(kind("1.9.0", "2.0.0-rc1"), Some(UpdateKind::PreRelease));
+        assert_eq!(kind("2.0.0-rc1", "2.0.0"), Some(UpdateKind::Patch));
+        // Downgrades are as big as the upgrade they undo
+        assert_eq!(kind("3.0.0", "2.5.0"), Some(UpdateKind::Major));
+        assert_eq!(
+            kind("5.3.1.RELEASE", "5.4.0.RELEASE"),
+            Some(UpdateKind::Minor)
+        );
+        // Nothing changed, also when written differently
+        assert_eq!(kind("1.0.0", "1.0.0"), None);
+        assert_eq!(kind("v1.0", "1.0.0"), None);
+        assert_eq!(kind("2.0.0-rc.1", "2.0.0-rc.1"), None);
+        assert_eq!(kind("sha256:abc", "sha256:def"), None);
+        assert_eq!(kind("", "1.0.0"), None);
+    }
+}
@@ -0,0 +1,164 @@
+//! Problems that don't stop the run but leave the notes less complete, collected for a summary
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::Mutex;
+
+/// What went wrong with a forge lookup
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ProblemKind {
+    /// The token was rejected or lacks access (HTTP 401 or 403)
+    InvalidToken,
+    /// Still rate limited after retrying
+    RateLimited,
+    /// A change request referenced by a commit doesn't exist
+    NotFound,
+    /// The API couldn't be reached
+    Network,
+    /// Any other error response
+    Api,
+}
+
+impl ProblemKind {
+    pub fn description(&self) -> &'static str {
+        match self {
+            ProblemKind::InvalidToken => "invalid or expired token",
+            ProblemKind::RateLimited => "rate limited",
+            ProblemKind::NotFound => "not found",
+            ProblemKind::Network => "network error",
+            ProblemKind::Api => "API error",
+        }
+    }
+}
+
+/// Error from a forge API, telling what kind of problem it is
+#[derive(Debug)]
+pub struct ForgeError {
+    pub kind: ProblemKind,
+    pub message: String,
+}
+
+impl ForgeError {
+    pub fn new(kind: ProblemKind, message: impl Into<String>) -> Self {
+        ForgeError {
+            kind,
+            message: message.into(),
+        }
+    }
+
+    /// Kind of the first `ForgeError` in the error's chain, `Api` for unclassified errors
+    pub fn kind_of(err: &anyhow::Error) -> ProblemKind {
+        err.chain()
+            .find_map(|cause| cause.downcast_ref::<ForgeError>())
+            .map_or(ProblemKind::Api, |err| err.kind)
+    }
+}
+
+impl fmt::Display for ForgeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for ForgeError {}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Warning {
+    pub kind: ProblemKind,
+    /// What was being looked up, e.g. "commit 1a2b3c4" or "PR #12"
+    pub subject: String,
+    pub message: String,
+}
+
+/// Warnings collected while commits are processed concurrently
+#[derive(Debug, Default)]
+pub struct Warnings(Mutex<Vec<Warning>>);
+
+impl Warnings {
+    pub fn push(&self, kind: ProblemKind, subject: impl Into<String>, message: impl Into<String>) {
+        self.0.lock().unwrap().push(Warning {
+            kind,
+            subject: subject.into(),
+            message: message.into(),
+        });
+    }
+
+    /// Record a failed lookup of `subject`
+    pub fn lookup_failed(&self, subject: impl Into<String>, err: &anyhow::Error) {
+        self.push(ForgeError::kind_of(err), subject, format!("{:#}", err));
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn to_vec(&self) -> Vec<Warning> {
+        self.0.lock().unwrap().clone()
+    }
+
+    /// One line per kind of problem with a count and the first occurrence, `None` without warnings
+    pub fn summary(&self) -> Option<String> {
+        let warnings = self.0.lock().unwrap();
+        if warnings.is_empty() {
+            return None;
+        }
+        let mut by_kind: BTreeMap<ProblemKind, Vec<&Warning>> = BTreeMap::new();
+        for warning in warnings.iter() {
+            by_kind.entry(warning.kind).or_default().push(warning);
+        }
+
+        let mut lines = vec![format!(
+            "Warning: {} lookup{} failed, the notes may be missing PR numbers or details:",
+            warnings.len(),
+            if warnings.len() == 1 { "" } else { "s" }
+        )];
+        for (kind, warnings) in by_kind {
+            let first = warnings[0];
+            lines.push(format!(
+                "- {} ({}), e.g. {}: {}",
+                kind.description(),
+                warnings.len(),
+                first.subject,
+                first.message
+            ));
+        }
+        Some(lines.join("\n"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::anyhow;
+
+    #[test]
+    fn test_kind_of_error() {
+        let err: anyhow::Error = ForgeError::new(ProblemKind::RateLimited, "slow down").into();
+        let err = err.context("Looking up commit abc");
+        assert_eq!(ForgeError::kind_of(&err), ProblemKind::RateLimited);
+        assert_eq!(ForgeError::kind_of(&anyhow!("odd")), ProblemKind::Api);
+    }
+
+    #[test]
+    fn test_summary_groups_by_kind() {
+        let warnings = Warnings::default();
+        assert_eq!(warnings.summary(), None);
+
+        let expired: anyhow::Error =
+            ForgeError::new(ProblemKind::InvalidToken, "Bad credentials").into();
+        warnings.push(ProblemKind::NotFound, "PR #99", "no such pull request");
+        warnings.lookup_failed("commit 1a2b3c4", &expired);
+        warnings.lookup_failed("commit 5d6e7f8", &expired);
+        assert_eq!(warnings.len(), 3);
+        assert_eq!(
+            warnings.summary().unwrap(),
+            "Warning: 3 lookups failed, the notes may be missing PR numbers or details:\n\
+             - invalid or expired token (2), e.g. commit 1a2b3c4: Bad credentials\n\
+             - not found (1), e.g. PR #99: no such pull request"
+        );
+    }
+}